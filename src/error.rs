@@ -0,0 +1,204 @@
+use std::fmt;
+
+/// Errors returned by the fallible constructors and lookups across the crate, in place of
+/// panicking on bad input.
+#[derive(Debug)]
+pub enum Error {
+    /// A major zone index outside 0-8
+    InvalidZoneIndex(usize),
+
+    /// A major zone number outside 1-9
+    InvalidZoneNumber(usize),
+
+    /// An adjacency value other than 0 or 1
+    InvalidAdjacency(i8),
+
+    /// No edge connects the two node indices
+    MissingEdge(usize, usize),
+
+    /// An edge refers to a node index that does not exist
+    DanglingNodeIndex(usize),
+
+    /// No node has the given id
+    UnknownNodeId(usize),
+
+    /// A toroidal canvas whose width or height is not a positive, finite number:
+    /// `(width, height)`
+    InvalidCanvasSize(f64, f64),
+
+    /// A caller-provided buffer holds fewer values than needed: `(needed, len)`
+    BufferTooSmall(usize, usize),
+
+    /// Serialized state written by a newer, unknown version of the format
+    UnsupportedVersion(u32),
+
+    /// A value could not be serialized or deserialized
+    Serde(serde_json::Error),
+
+    /// Hand-written JSON has one or more malformed or out-of-range fields
+    InvalidFields(Vec<FieldError>),
+
+    /// An image could not be encoded
+    #[cfg(feature = "image")]
+    Image(png::EncodingError),
+
+    /// An image too large to allocate was requested: `(width, height)`
+    #[cfg(feature = "image")]
+    ImageTooLarge(u32, u32),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// A single malformed or out-of-range field found while validating JSON, such as an edge
+/// referring to a node that does not exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    /// Where the item holding the field sits in the document, e.g. `edges[2]`, or empty for a
+    /// field of the document itself
+    pub location: String,
+
+    /// Name of the field, e.g. `node1_idx`
+    pub field: String,
+
+    /// What is wrong with the field
+    pub problem: String,
+}
+
+impl FieldError {
+    pub fn new(location: &str, field: &str, problem: &str) -> FieldError {
+        FieldError {
+            location: location.to_string(),
+            field: field.to_string(),
+            problem: problem.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for FieldError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.location.is_empty() {
+            write!(f, "{}: {}", self.field, self.problem)
+        } else {
+            write!(f, "{}.{}: {}", self.location, self.field, self.problem)
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidZoneIndex(index) => {
+                write!(f, "Invalid index {} for MajorZone (expected 0-8)", index)
+            }
+            Error::InvalidZoneNumber(number) => {
+                write!(
+                    f,
+                    "Invalid zone number {} for MajorZone (expected 1-9)",
+                    number
+                )
+            }
+            Error::InvalidAdjacency(value) => {
+                write!(f, "Invalid adjacency value {} (expected 0 or 1)", value)
+            }
+            Error::MissingEdge(node1_idx, node2_idx) => {
+                write!(f, "No edge connects nodes {} and {}", node1_idx, node2_idx)
+            }
+            Error::DanglingNodeIndex(idx) => {
+                write!(f, "Edge refers to node index {}, which does not exist", idx)
+            }
+            Error::UnknownNodeId(id) => write!(f, "No node has id {}", id),
+            Error::InvalidCanvasSize(width, height) => {
+                write!(
+                    f,
+                    "Invalid canvas size {} x {} (expected positive, finite dimensions)",
+                    width, height
+                )
+            }
+            Error::BufferTooSmall(needed, len) => {
+                write!(f, "Buffer of length {} is too small, need {}", len, needed)
+            }
+            Error::UnsupportedVersion(version) => {
+                write!(f, "Unsupported serialization version {}", version)
+            }
+            Error::Serde(error) => write!(f, "Serialization error: {}", error),
+            Error::InvalidFields(errors) => {
+                write!(f, "{} invalid field(s)", errors.len())?;
+                for error in errors.iter() {
+                    write!(f, "\n  {}", error)?;
+                }
+                Ok(())
+            }
+            #[cfg(feature = "image")]
+            Error::Image(error) => write!(f, "Image encoding error: {}", error),
+            #[cfg(feature = "image")]
+            Error::ImageTooLarge(width, height) => {
+                write!(f, "Image of {} x {} pixels is too large", width, height)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Serde(error) => Some(error),
+            #[cfg(feature = "image")]
+            Error::Image(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(error: serde_json::Error) -> Error {
+        Error::Serde(error)
+    }
+}
+
+#[cfg(feature = "image")]
+impl From<png::EncodingError> for Error {
+    fn from(error: png::EncodingError) -> Error {
+        Error::Image(error)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_error_display() {
+        assert_eq!(
+            Error::InvalidZoneIndex(9).to_string(),
+            "Invalid index 9 for MajorZone (expected 0-8)"
+        );
+        assert_eq!(
+            Error::MissingEdge(0, 2).to_string(),
+            "No edge connects nodes 0 and 2"
+        );
+    }
+
+    #[test]
+    pub fn test_invalid_fields_display() {
+        let error = Error::InvalidFields(vec![
+            FieldError::new("edges[0]", "node2_idx", "index 7 is out of range"),
+            FieldError::new("nodes[1]", "position", "missing"),
+        ]);
+        assert_eq!(
+            FieldError::new("", "edges", "missing").to_string(),
+            "edges: missing"
+        );
+        assert_eq!(
+            error.to_string(),
+            "2 invalid field(s)\n  edges[0].node2_idx: index 7 is out of range\n  nodes[1].position: missing"
+        );
+    }
+
+    #[test]
+    pub fn test_error_from_serde() {
+        let serde_error = serde_json::from_str::<f64>("not json").unwrap_err();
+        let error: Error = serde_error.into();
+        assert!(matches!(error, Error::Serde(_)));
+        assert!(std::error::Error::source(&error).is_some());
+    }
+}
@@ -0,0 +1,91 @@
+use crate::graph::edge::Edge;
+
+/// The edges of a graph in compressed sparse row form: the neighbors of every node sit next to
+/// each other in one contiguous array, so iterating over them touches no hash map and stays in
+/// cache. Each undirected edge appears in the rows of both of its nodes.
+///
+/// The neighbors of node `i` are `col_indices[row_offsets[i]..row_offsets[i + 1]]`, in ascending
+/// order, and `weights` holds the weight of the edge to each of them at the same positions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsrGraph {
+    pub row_offsets: Vec<usize>,
+    pub col_indices: Vec<usize>,
+    pub weights: Vec<f64>,
+}
+
+impl CsrGraph {
+    /// Builds the rows for `n_nodes` nodes from a list of undirected edges. Edges with an
+    /// endpoint outside `0..n_nodes` are left out.
+    pub fn from_edges(n_nodes: usize, edges: &[Edge]) -> Self {
+        let mut rows: Vec<Vec<(usize, f64)>> = vec![vec![]; n_nodes];
+        for edge in edges {
+            let (i, j) = (edge.node1_idx, edge.node2_idx);
+            if i >= n_nodes || j >= n_nodes {
+                continue;
+            }
+            rows[i].push((j, edge.weight));
+            if i != j {
+                rows[j].push((i, edge.weight));
+            }
+        }
+
+        let mut row_offsets = Vec::with_capacity(n_nodes + 1);
+        let mut col_indices = Vec::new();
+        let mut weights = Vec::new();
+        row_offsets.push(0);
+        for mut row in rows {
+            row.sort_by_key(|&(neighbor, _)| neighbor);
+            for (neighbor, weight) in row {
+                col_indices.push(neighbor);
+                weights.push(weight);
+            }
+            row_offsets.push(col_indices.len());
+        }
+
+        CsrGraph {
+            row_offsets,
+            col_indices,
+            weights,
+        }
+    }
+
+    pub fn n_nodes(&self) -> usize {
+        self.row_offsets.len() - 1
+    }
+
+    /// Returns the neighbors of the node at `node_idx`, in ascending order.
+    pub fn neighbors(&self, node_idx: usize) -> &[usize] {
+        &self.col_indices[self.row_offsets[node_idx]..self.row_offsets[node_idx + 1]]
+    }
+
+    /// Returns the weights of the edges to the neighbors of the node at `node_idx`, in the same
+    /// order as [CsrGraph::neighbors](CsrGraph::neighbors).
+    pub fn neighbor_weights(&self, node_idx: usize) -> &[f64] {
+        &self.weights[self.row_offsets[node_idx]..self.row_offsets[node_idx + 1]]
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_from_edges() {
+        // a path 2 - 0 - 3 and an isolated node 1, plus an edge to a missing node
+        let edges = vec![
+            Edge::new(0, 3, 1.5),
+            Edge::new(2, 0, 0.5),
+            Edge::new(3, 9, 1.0),
+        ];
+        let csr = CsrGraph::from_edges(4, &edges);
+
+        assert_eq!(csr.n_nodes(), 4);
+        assert_eq!(csr.row_offsets, vec![0, 2, 2, 3, 4]);
+        assert_eq!(csr.neighbors(0), &[2, 3]);
+        assert_eq!(csr.neighbor_weights(0), &[0.5, 1.5]);
+        assert!(csr.neighbors(1).is_empty());
+        assert_eq!(csr.neighbors(2), &[0]);
+        assert_eq!(csr.neighbors(3), &[0]);
+        assert_eq!(csr.neighbor_weights(3), &[1.5]);
+    }
+}
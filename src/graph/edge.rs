@@ -1,19 +1,82 @@
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+use serde::{Deserialize, Serialize};
+
+/// How the line for an edge is drawn.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Serialize, Deserialize)]
+pub enum LineStyle {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+/// How the weights of parallel edges are combined when they are merged into a single edge.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeMerge {
+    /// The merged edge carries the sum of the weights
+    #[default]
+    Sum,
+
+    /// The merged edge carries the largest of the weights
+    Max,
+}
+
+/// The styling fields may be left out of hand-written JSON, and take the same defaults as
+/// [Edge::new](Edge::new).
+#[derive(Debug, Clone, PartialEq, PartialOrd, Serialize, Deserialize)]
 pub struct Edge {
     pub node1_idx: usize, // Index of the first node
     pub node2_idx: usize, // Index of the second node
-    pub weight: f64,      // Correlation strength
+    pub weight: f64,      // Correlation strength, or a target distance (see WeightSemantics)
+    #[serde(default = "default_color")]
+    pub color: String, // Line color, in any format the renderer accepts
+    #[serde(default = "default_width")]
+    pub width: f64, // Line width
+    #[serde(default)]
+    pub style: LineStyle, // Line style
+}
+
+fn default_color() -> String {
+    "black".to_string()
+}
+
+fn default_width() -> f64 {
+    1.0
 }
 
 impl Edge {
+    /// Creates an edge drawn as a solid black line of width 1.
     pub fn new(node1_idx: usize, node2_idx: usize, weight: f64) -> Self {
         Edge {
             node1_idx,
             node2_idx,
             weight,
+            color: default_color(),
+            width: default_width(),
+            style: LineStyle::default(),
         }
     }
 
+    /// Creates an edge between the nodes at `node1_idx` and `node2_idx`. This is the same as
+    /// [Edge::new](Edge::new), under a name that reads better at call sites.
+    pub fn between(node1_idx: usize, node2_idx: usize, weight: f64) -> Self {
+        Edge::new(node1_idx, node2_idx, weight)
+    }
+
+    pub fn with_color(mut self, color: &str) -> Edge {
+        self.color = color.to_string();
+        self
+    }
+
+    pub fn with_width(mut self, width: f64) -> Edge {
+        self.width = width;
+        self
+    }
+
+    pub fn with_style(mut self, style: LineStyle) -> Edge {
+        self.style = style;
+        self
+    }
+
     pub fn get_node1_idx(&self) -> usize {
         self.node1_idx
     }
@@ -42,3 +105,45 @@ impl Edge {
         self.node1_idx == node_idx || self.node2_idx == node_idx
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_edge_style() {
+        let plain = Edge::new(0, 1, 2.0);
+        assert_eq!(plain.color, "black".to_string());
+        assert_eq!(plain.width, 1.0);
+        assert_eq!(plain.style, LineStyle::Solid);
+
+        let styled = Edge::new(0, 1, 2.0)
+            .with_color("red")
+            .with_width(3.0)
+            .with_style(LineStyle::Dashed);
+        assert_eq!(styled.color, "red".to_string());
+        assert_eq!(styled.width, 3.0);
+        assert_eq!(styled.style, LineStyle::Dashed);
+        assert_eq!(styled.weight, plain.weight);
+    }
+
+    #[test]
+    pub fn test_edge_between() {
+        let edge = Edge::between(3, 5, 0.5);
+        assert_eq!(edge.node1_idx, 3);
+        assert_eq!(edge.node2_idx, 5);
+        assert_eq!(edge.weight, 0.5);
+        assert_eq!(edge, Edge::new(3, 5, 0.5));
+    }
+
+    #[test]
+    pub fn test_edge_style_serde() {
+        let edge = Edge::new(2, 3, 0.5)
+            .with_color("#336699")
+            .with_width(0.25)
+            .with_style(LineStyle::Dotted);
+        let json = serde_json::to_string(&edge).unwrap();
+        assert!(json.contains("\"style\":\"Dotted\""));
+        assert_eq!(serde_json::from_str::<Edge>(&json).unwrap(), edge);
+    }
+}
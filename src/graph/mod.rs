@@ -1,3 +1,4 @@
+pub mod csr_graph;
 pub mod edge;
 pub mod node;
 pub mod zone;
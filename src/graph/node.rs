@@ -1,15 +1,95 @@
 use crate::math::vector_2d::Vector2D;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+/// Fields left at their default values are omitted when a node is serialized, and filled back in
+/// when it is deserialized, which keeps the JSON for a default-styled node short.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Node {
     pub id: usize,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub label: String,
     pub position: Vector2D<f64>,
+    #[serde(
+        default = "default_velocity",
+        skip_serializing_if = "is_default_velocity"
+    )]
     pub velocity: Vector2D<f64>,
+    #[serde(default = "default_one", skip_serializing_if = "is_one")]
     pub mass: f64,
+    #[serde(default = "default_one", skip_serializing_if = "is_one")]
     pub radius: f64,
+    #[serde(
+        default = "default_edge_color",
+        skip_serializing_if = "is_default_edge_color"
+    )]
     pub edge_color: String,
+    #[serde(default = "default_fill", skip_serializing_if = "is_default_fill")]
     pub fill: String,
+
+    /// Community the node belongs to, if any. Nodes sharing a group can be pulled together more
+    /// tightly than nodes in different groups.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<usize>,
+
+    /// Set of nodes that do not repel each other, if any. Two nodes sharing a no-repel group only
+    /// interact through the edges between them, which keeps tight clusters compact, while still
+    /// repelling every node outside the group.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_repel_group: Option<usize>,
+
+    /// Whether the node is held in place. A fixed node is never moved by the simulation, though
+    /// it still exerts forces on every other node.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub fixed: bool,
+
+    /// Whether the node is drawn. A hidden node still takes part in the simulation, so the layout
+    /// does not jump when it is revealed; only renderers skip it.
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub visible: bool,
+}
+
+fn default_velocity() -> Vector2D<f64> {
+    Vector2D::from_xy(0.0, 0.0)
+}
+
+fn is_default_velocity(velocity: &Vector2D<f64>) -> bool {
+    *velocity == default_velocity()
+}
+
+fn default_one() -> f64 {
+    1.0
+}
+
+fn is_one(value: &f64) -> bool {
+    *value == 1.0
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
+fn default_edge_color() -> String {
+    "black".to_string()
+}
+
+fn is_default_edge_color(color: &str) -> bool {
+    color == "black"
+}
+
+fn default_fill() -> String {
+    "transparent".to_string()
+}
+
+fn is_default_fill(fill: &str) -> bool {
+    fill == "transparent"
 }
 
 impl Node {
@@ -33,11 +113,15 @@ impl Node {
             id: 0,
             label: "".to_string(),
             position: Vector2D::from_xy(1.0, 1.0),
-            mass: 1.0,
-            velocity: Vector2D::from_xy(0.0, 0.0),
-            radius: 1.0,
-            edge_color: "black".to_string(),
-            fill: "transparent".to_string(),
+            mass: default_one(),
+            velocity: default_velocity(),
+            radius: default_one(),
+            edge_color: default_edge_color(),
+            fill: default_fill(),
+            group: None,
+            no_repel_group: None,
+            fixed: false,
+            visible: default_true(),
         }
     }
 }
@@ -62,6 +146,64 @@ impl Node {
     pub fn update_label(&mut self, label: &str) {
         self.label = label.to_string();
     }
+
+    /// Whether this node and `other` repel each other, which they do unless they share a
+    /// no-repel group.
+    pub fn repels(&self, other: &Node) -> bool {
+        match (self.no_repel_group, other.no_repel_group) {
+            (Some(g1), Some(g2)) => g1 != g2,
+            _ => true,
+        }
+    }
+}
+
+impl Node {
+    /// Returns a node with the given id and position and default values for everything else. This
+    /// is shorthand for building one with a NodeBuilder.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crate::graph::node::Node;
+    /// use crate::math::vector_2d::Vector2D;
+    ///
+    /// let node = Node::at(1, Vector2D::from_xy(2.0, 3.0));
+    /// ```
+    pub fn at(id: usize, position: Vector2D<f64>) -> Node {
+        Node::new().id(id).position(position).build()
+    }
+
+    /// Returns the node with its position replaced, leaving every other field unchanged. This is
+    /// a terser alternative to rebuilding the node with a NodeBuilder.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use crate::graph::node::Node;
+    /// use crate::math::vector_2d::Vector2D;
+    ///
+    /// let node = Node::new().id(1).build();
+    /// let moved = node.with_position(Vector2D::from_xy(2.0, 3.0));
+    /// ```
+    pub fn with_position(mut self, position: Vector2D<f64>) -> Node {
+        self.position = position;
+        self
+    }
+
+    pub fn with_velocity(mut self, velocity: Vector2D<f64>) -> Node {
+        self.velocity = velocity;
+        self
+    }
+
+    pub fn with_fill(mut self, fill: &str) -> Node {
+        self.fill = fill.to_string();
+        self
+    }
+
+    pub fn with_label(mut self, label: &str) -> Node {
+        self.label = label.to_string();
+        self
+    }
 }
 
 impl Default for Node {
@@ -79,6 +221,10 @@ pub struct NodeBuilder {
     radius: f64,
     edge_color: String,
     fill: String,
+    group: Option<usize>,
+    no_repel_group: Option<usize>,
+    fixed: bool,
+    visible: bool,
 }
 
 impl NodeBuilder {
@@ -122,6 +268,26 @@ impl NodeBuilder {
         self
     }
 
+    pub fn group(mut self, group: usize) -> Self {
+        self.group = Some(group);
+        self
+    }
+
+    pub fn no_repel_group(mut self, no_repel_group: usize) -> Self {
+        self.no_repel_group = Some(no_repel_group);
+        self
+    }
+
+    pub fn fixed(mut self, fixed: bool) -> Self {
+        self.fixed = fixed;
+        self
+    }
+
+    pub fn visible(mut self, visible: bool) -> Self {
+        self.visible = visible;
+        self
+    }
+
     pub fn build(self) -> Node {
         Node {
             id: self.id,
@@ -132,6 +298,10 @@ impl NodeBuilder {
             radius: self.radius,
             edge_color: self.edge_color,
             fill: self.fill,
+            group: self.group,
+            no_repel_group: self.no_repel_group,
+            fixed: self.fixed,
+            visible: self.visible,
         }
     }
 }
@@ -156,6 +326,10 @@ pub mod tests {
             .radius(6.0)
             .edge_color("red")
             .fill("blue")
+            .group(2)
+            .no_repel_group(3)
+            .fixed(true)
+            .visible(false)
             .build();
 
         // Test that the default values are correctly set if no builder methods are called
@@ -170,6 +344,10 @@ pub mod tests {
         assert_eq!(node.radius, 6.0);
         assert_eq!(node.edge_color, "red".to_string());
         assert_eq!(node.fill, "blue".to_string());
+        assert_eq!(node.group, Some(2));
+        assert_eq!(node.no_repel_group, Some(3));
+        assert!(node.fixed);
+        assert!(!node.visible);
 
         // Default values check
         assert_eq!(node2.id, 0);
@@ -180,5 +358,107 @@ pub mod tests {
         assert_eq!(node2.radius, 1.0);
         assert_eq!(node2.edge_color, "black".to_string());
         assert_eq!(node2.fill, "transparent".to_string());
+        assert_eq!(node2.group, None);
+        assert_eq!(node2.no_repel_group, None);
+        assert!(!node2.fixed);
+        assert!(node2.visible);
+    }
+
+    #[test]
+    pub fn test_node_at() {
+        let position = Vector2D::from_xy(2.0, -3.0);
+        let node = Node::at(4, position);
+        let built = Node::new().id(4).position(position).build();
+
+        assert_eq!(node.id, 4);
+        assert_eq!(node.position, position);
+        assert_eq!(
+            serde_json::to_string(&node).unwrap(),
+            serde_json::to_string(&built).unwrap()
+        );
+    }
+
+    #[test]
+    pub fn test_node_with_methods() {
+        let node = Node::new()
+            .id(1)
+            .label("test")
+            .mass(5.0)
+            .radius(6.0)
+            .edge_color("red")
+            .fill("blue")
+            .build();
+
+        let position = Vector2D::from_xy(7.0, 8.0);
+        let moved = node.clone().with_position(position);
+        assert_eq!(moved.position, position);
+        assert_eq!(moved.velocity, node.velocity);
+        assert_eq!(moved.id, node.id);
+        assert_eq!(moved.label, node.label);
+        assert_eq!(moved.mass, node.mass);
+        assert_eq!(moved.radius, node.radius);
+        assert_eq!(moved.edge_color, node.edge_color);
+        assert_eq!(moved.fill, node.fill);
+
+        let velocity = Vector2D::from_xy(-1.0, 2.0);
+        let moving = node.clone().with_velocity(velocity);
+        assert_eq!(moving.velocity, velocity);
+        assert_eq!(moving.position, node.position);
+        assert_eq!(moving.fill, node.fill);
+
+        let filled = node.clone().with_fill("green");
+        assert_eq!(filled.fill, "green".to_string());
+        assert_eq!(filled.label, node.label);
+        assert_eq!(filled.position, node.position);
+
+        let relabeled = node.clone().with_label("renamed");
+        assert_eq!(relabeled.label, "renamed".to_string());
+        assert_eq!(relabeled.fill, node.fill);
+        assert_eq!(relabeled.id, node.id);
+    }
+
+    #[test]
+    pub fn test_node_serde_omits_defaults() {
+        let plain = Node::new()
+            .id(3)
+            .position(Vector2D::from_xy(1.5, -2.0))
+            .build();
+        let styled = plain
+            .clone()
+            .with_label("styled")
+            .with_fill("blue")
+            .with_velocity(Vector2D::from_xy(1.0, 0.0));
+
+        let plain_json = serde_json::to_string(&plain).unwrap();
+        let styled_json = serde_json::to_string(&styled).unwrap();
+        assert_eq!(plain_json, r#"{"id":3,"position":{"x":1.5,"y":-2.0}}"#);
+        assert!(plain_json.len() * 2 < styled_json.len());
+
+        // the omitted fields come back as their defaults
+        let restored: Node = serde_json::from_str(&plain_json).unwrap();
+        assert_eq!(restored.id, plain.id);
+        assert_eq!(restored.label, plain.label);
+        assert_eq!(restored.position, plain.position);
+        assert_eq!(restored.velocity, plain.velocity);
+        assert_eq!(restored.mass, plain.mass);
+        assert_eq!(restored.radius, plain.radius);
+        assert_eq!(restored.edge_color, plain.edge_color);
+        assert_eq!(restored.fill, plain.fill);
+        assert_eq!(restored.group, plain.group);
+        assert_eq!(restored.no_repel_group, plain.no_repel_group);
+        assert_eq!(restored.fixed, plain.fixed);
+        assert_eq!(restored.visible, plain.visible);
+
+        let restored: Node = serde_json::from_str(&styled_json).unwrap();
+        assert_eq!(restored.label, "styled".to_string());
+        assert_eq!(restored.fill, "blue".to_string());
+        assert_eq!(restored.velocity, Vector2D::from_xy(1.0, 0.0));
+
+        // only a hidden node records its visibility
+        let mut hidden = plain.clone();
+        hidden.visible = false;
+        let hidden_json = serde_json::to_string(&hidden).unwrap();
+        assert!(hidden_json.contains("\"visible\":false"));
+        assert!(!serde_json::from_str::<Node>(&hidden_json).unwrap().visible);
     }
 }
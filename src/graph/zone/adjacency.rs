@@ -1,3 +1,5 @@
+use crate::error::{Error, Result};
+
 pub enum Adjacency {
     Adjacent,
     NotAdjacent,
@@ -5,10 +7,16 @@ pub enum Adjacency {
 
 impl Adjacency {
     pub fn from_int(adj: i8) -> Self {
+        Adjacency::try_from_int(adj).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Fallible version of [Adjacency::from_int](Adjacency::from_int), returning
+    /// [Error::InvalidAdjacency] for a value other than 0 or 1 instead of panicking.
+    pub fn try_from_int(adj: i8) -> Result<Self> {
         match adj {
-            0 => Adjacency::NotAdjacent,
-            1 => Adjacency::Adjacent,
-            _ => panic!("Invalid adjacency value"),
+            0 => Ok(Adjacency::NotAdjacent),
+            1 => Ok(Adjacency::Adjacent),
+            _ => Err(Error::InvalidAdjacency(adj)),
         }
     }
 
@@ -33,3 +41,22 @@ impl Adjacency {
         }
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_try_from_int() {
+        assert!(Adjacency::try_from_int(1).unwrap().to_bool());
+        assert!(!Adjacency::try_from_int(0).unwrap().to_bool());
+        assert!(matches!(
+            Adjacency::try_from_int(2),
+            Err(Error::InvalidAdjacency(2))
+        ));
+        assert!(matches!(
+            Adjacency::try_from_int(-1),
+            Err(Error::InvalidAdjacency(-1))
+        ));
+    }
+}
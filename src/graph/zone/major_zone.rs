@@ -1,3 +1,4 @@
+use crate::error::{Error, Result};
 use crate::graph::node::Node;
 use crate::math::vector_2d::Vector2D;
 
@@ -63,17 +64,23 @@ impl MajorZone {
     /// +---+---+---+
     ///
     pub fn from_index(index: usize) -> Self {
+        MajorZone::try_from_index(index).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Fallible version of [MajorZone::from_index](MajorZone::from_index), returning
+    /// [Error::InvalidZoneIndex] for an index above 8 instead of panicking.
+    pub fn try_from_index(index: usize) -> Result<Self> {
         match index {
-            0 => MajorZone::TopLeft,
-            1 => MajorZone::TopMiddle,
-            2 => MajorZone::TopRight,
-            3 => MajorZone::MiddleLeft,
-            4 => MajorZone::MiddleMiddle,
-            5 => MajorZone::MiddleRight,
-            6 => MajorZone::BottomLeft,
-            7 => MajorZone::BottomMiddle,
-            8 => MajorZone::BottomRight,
-            _ => panic!("Invalid index for MajorZone"),
+            0 => Ok(MajorZone::TopLeft),
+            1 => Ok(MajorZone::TopMiddle),
+            2 => Ok(MajorZone::TopRight),
+            3 => Ok(MajorZone::MiddleLeft),
+            4 => Ok(MajorZone::MiddleMiddle),
+            5 => Ok(MajorZone::MiddleRight),
+            6 => Ok(MajorZone::BottomLeft),
+            7 => Ok(MajorZone::BottomMiddle),
+            8 => Ok(MajorZone::BottomRight),
+            _ => Err(Error::InvalidZoneIndex(index)),
         }
     }
 
@@ -91,17 +98,16 @@ impl MajorZone {
     /// +---+---+---+
     ///
     pub fn from_zone_number(zone_number: usize) -> Self {
+        MajorZone::try_from_zone_number(zone_number).unwrap_or_else(|error| panic!("{}", error))
+    }
+
+    /// Fallible version of [MajorZone::from_zone_number](MajorZone::from_zone_number), returning
+    /// [Error::InvalidZoneNumber] for a zone number outside 1-9 instead of panicking.
+    pub fn try_from_zone_number(zone_number: usize) -> Result<Self> {
         match zone_number {
-            1 => MajorZone::TopLeft,
-            2 => MajorZone::TopMiddle,
-            3 => MajorZone::TopRight,
-            4 => MajorZone::MiddleLeft,
-            5 => MajorZone::MiddleMiddle,
-            6 => MajorZone::MiddleRight,
-            7 => MajorZone::BottomLeft,
-            8 => MajorZone::BottomMiddle,
-            9 => MajorZone::BottomRight,
-            _ => panic!("Invalid zone number for MajorZone"),
+            0 => Err(Error::InvalidZoneNumber(zone_number)),
+            _ => MajorZone::try_from_index(zone_number - 1)
+                .map_err(|_| Error::InvalidZoneNumber(zone_number)),
         }
     }
 
@@ -262,6 +268,47 @@ impl MajorZone {
         self.adjacent().contains(other)
     }
 
+    /// Returns the major zone containing `point`, for a canvas spanning `top_left` to
+    /// `bottom_right`. The y axis points down, so `top_left` has the smaller coordinates.
+    ///
+    /// ### Notes
+    ///
+    /// Each zone covers the half-open intervals `[lo, hi)` in both directions, so a point lying
+    /// exactly on an interior gridline belongs to the zone to its right (or below it). The
+    /// rightmost column and bottom row also absorb the canvas's max edge, and points outside the
+    /// canvas are assigned to the nearest zone. A point therefore never flips between zones
+    /// unless it actually crosses a gridline.
+    ///
+    /// ### See also
+    ///
+    /// [MajorZone::gridlines](MajorZone::gridlines)
+    ///
+    pub fn major_zone_of(
+        point: &Vector2D<f64>,
+        top_left: &Vector2D<f64>,
+        bottom_right: &Vector2D<f64>,
+    ) -> Self {
+        let (x_lines, y_lines) = MajorZone::gridlines(top_left, bottom_right);
+        let column = x_lines.iter().filter(|&&line| point.x >= line).count();
+        let row = y_lines.iter().filter(|&&line| point.y >= line).count();
+        MajorZone::from_index(3 * row + column)
+    }
+
+    /// Returns the two interior vertical gridlines (x coordinates) and the two interior
+    /// horizontal gridlines (y coordinates) dividing the canvas into the 9 major zones.
+    ///
+    pub fn gridlines(
+        top_left: &Vector2D<f64>,
+        bottom_right: &Vector2D<f64>,
+    ) -> ([f64; 2], [f64; 2]) {
+        let width = bottom_right.x - top_left.x;
+        let height = bottom_right.y - top_left.y;
+        (
+            [top_left.x + width / 3.0, top_left.x + 2.0 * width / 3.0],
+            [top_left.y + height / 3.0, top_left.y + 2.0 * height / 3.0],
+        )
+    }
+
     /// Boolean to test if a major zone is NOT adjacent to another major zone.
     ///
     /// ### See also
@@ -272,3 +319,96 @@ impl MajorZone {
         !self.is_adjacent_to(other)
     }
 }
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    fn canvas() -> (Vector2D<f64>, Vector2D<f64>) {
+        (Vector2D::from_xy(0.0, 0.0), Vector2D::from_xy(3.0, 3.0))
+    }
+
+    #[test]
+    pub fn test_try_from_index() {
+        for index in 0..9 {
+            let zone = MajorZone::try_from_index(index).unwrap();
+            assert_eq!(zone.get_zone_index(), index);
+        }
+        assert!(matches!(
+            MajorZone::try_from_index(9),
+            Err(Error::InvalidZoneIndex(9))
+        ));
+    }
+
+    #[test]
+    pub fn test_try_from_zone_number() {
+        for number in 1..=9 {
+            let zone = MajorZone::try_from_zone_number(number).unwrap();
+            assert_eq!(zone.get_zone_number(), number);
+        }
+        assert!(matches!(
+            MajorZone::try_from_zone_number(0),
+            Err(Error::InvalidZoneNumber(0))
+        ));
+        assert!(matches!(
+            MajorZone::try_from_zone_number(10),
+            Err(Error::InvalidZoneNumber(10))
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid index 9 for MajorZone")]
+    pub fn test_from_index_panics() {
+        MajorZone::from_index(9);
+    }
+
+    #[test]
+    pub fn test_major_zone_of_interior_points() {
+        let (top_left, bottom_right) = canvas();
+        for index in 0..9 {
+            let point = Vector2D::from_xy((index % 3) as f64 + 0.5, (index / 3) as f64 + 0.5);
+            assert_eq!(
+                MajorZone::major_zone_of(&point, &top_left, &bottom_right),
+                MajorZone::from_index(index)
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_major_zone_of_gridlines() {
+        let (top_left, bottom_right) = canvas();
+        let (x_lines, y_lines) = MajorZone::gridlines(&top_left, &bottom_right);
+        let zone_of = |x: f64, y: f64| {
+            MajorZone::major_zone_of(&Vector2D::from_xy(x, y), &top_left, &bottom_right)
+        };
+
+        // points on an interior gridline belong to the zone to the right of / below it
+        assert_eq!(zone_of(x_lines[0], 0.5), MajorZone::TopMiddle);
+        assert_eq!(zone_of(x_lines[1], 0.5), MajorZone::TopRight);
+        assert_eq!(zone_of(0.5, y_lines[0]), MajorZone::MiddleLeft);
+        assert_eq!(zone_of(0.5, y_lines[1]), MajorZone::BottomLeft);
+        assert_eq!(zone_of(x_lines[0], y_lines[0]), MajorZone::MiddleMiddle);
+        assert_eq!(zone_of(x_lines[1], y_lines[1]), MajorZone::BottomRight);
+
+        // the canvas edges belong to the outer zones
+        assert_eq!(zone_of(0.0, 0.0), MajorZone::TopLeft);
+        assert_eq!(zone_of(3.0, 3.0), MajorZone::BottomRight);
+        assert_eq!(zone_of(3.0, 0.0), MajorZone::TopRight);
+
+        // the assignment does not change when repeated
+        for _ in 0..10 {
+            assert_eq!(zone_of(x_lines[0], y_lines[1]), MajorZone::BottomMiddle);
+        }
+    }
+
+    #[test]
+    pub fn test_major_zone_of_outside_canvas() {
+        let (top_left, bottom_right) = canvas();
+        let zone_of = |x: f64, y: f64| {
+            MajorZone::major_zone_of(&Vector2D::from_xy(x, y), &top_left, &bottom_right)
+        };
+        assert_eq!(zone_of(-1.0, -1.0), MajorZone::TopLeft);
+        assert_eq!(zone_of(10.0, 1.5), MajorZone::MiddleRight);
+        assert_eq!(zone_of(1.5, 10.0), MajorZone::BottomMiddle);
+    }
+}
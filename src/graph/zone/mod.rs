@@ -1,3 +1,5 @@
 pub mod adjacency;
 pub mod major_zone;
 pub mod minor_zone;
+pub mod quad_tree;
+pub mod spatial_hash_grid;
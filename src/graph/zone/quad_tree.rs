@@ -0,0 +1,257 @@
+use crate::math::vector_2d::Vector2D;
+
+/// Depth beyond which cells stop splitting, so that coincident points cannot subdivide forever.
+const MAX_DEPTH: usize = 32;
+
+/// A recursive quadtree over point masses. Unlike the fixed 3x3 major zones and 2x2 minor zones,
+/// a cell splits into four quadrants only when it holds more than `capacity` points, so the tree
+/// is deeper where the points are clustered. Every cell tracks the total mass and center of mass
+/// of the points below it, which is what a Barnes-Hut approximation needs.
+///
+/// Quadrants are ordered top left, top right, bottom left, bottom right, with the y axis pointing
+/// down. As with [MajorZone::major_zone_of](crate::graph::zone::major_zone::MajorZone::major_zone_of),
+/// a point on a dividing line belongs to the quadrant to its right or below it.
+#[derive(Debug, Clone)]
+pub struct QuadTree {
+    top_left: Vector2D<f64>,
+    bottom_right: Vector2D<f64>,
+    capacity: usize,
+    depth: usize,
+    total_mass: f64,
+    weighted_position: Vector2D<f64>,
+    points: Vec<(Vector2D<f64>, f64)>,
+    children: Vec<QuadTree>,
+}
+
+impl QuadTree {
+    /// Creates an empty tree covering the rectangle from `top_left` to `bottom_right`, whose
+    /// cells split once they hold more than `capacity` points.
+    pub fn new(top_left: Vector2D<f64>, bottom_right: Vector2D<f64>, capacity: usize) -> Self {
+        QuadTree::with_depth(top_left, bottom_right, capacity.max(1), 0)
+    }
+
+    fn with_depth(
+        top_left: Vector2D<f64>,
+        bottom_right: Vector2D<f64>,
+        capacity: usize,
+        depth: usize,
+    ) -> Self {
+        QuadTree {
+            top_left,
+            bottom_right,
+            capacity,
+            depth,
+            total_mass: 0.0,
+            weighted_position: Vector2D::from_xy(0.0, 0.0),
+            points: vec![],
+            children: vec![],
+        }
+    }
+
+    /// Adds a point mass to the tree. Points outside the tree's rectangle are kept in the cell
+    /// nearest to them.
+    pub fn insert(&mut self, position: Vector2D<f64>, mass: f64) {
+        self.total_mass += mass;
+        self.weighted_position += position * mass;
+
+        if self.is_leaf() {
+            self.points.push((position, mass));
+            if self.points.len() > self.capacity && self.depth < MAX_DEPTH {
+                self.split();
+            }
+        } else {
+            let quadrant = self.quadrant_of(&position);
+            self.children[quadrant].insert(position, mass);
+        }
+    }
+
+    /// Boolean to test if the cell has not been subdivided.
+    pub fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+
+    /// Returns the total mass of the points in the cell.
+    pub fn total_mass(&self) -> f64 {
+        self.total_mass
+    }
+
+    /// Returns the mass-weighted average position of the points in the cell. An empty cell (or
+    /// one with zero total mass) has its center of mass at the center of its rectangle.
+    pub fn center_of_mass(&self) -> Vector2D<f64> {
+        if self.total_mass == 0.0 {
+            return (self.top_left + self.bottom_right) / 2.0;
+        }
+        self.weighted_position / self.total_mass
+    }
+
+    /// Returns the number of levels below this cell, so a leaf has depth 0.
+    pub fn depth(&self) -> usize {
+        self.children
+            .iter()
+            .map(|child| child.depth() + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Calls `f(position, mass)` for every point mass that should act on `point` under the
+    /// Barnes-Hut criterion: a cell whose width divided by its distance from `point` is below
+    /// `theta` is summarized by its center of mass and total mass, and any other cell is opened.
+    /// A `theta` of 0 visits every point individually. Points at `point` itself are included, so
+    /// callers should skip zero distances.
+    pub fn for_each_relevant<F: FnMut(Vector2D<f64>, f64)>(
+        &self,
+        point: &Vector2D<f64>,
+        theta: f64,
+        f: &mut F,
+    ) {
+        if self.total_mass == 0.0 && self.points.is_empty() {
+            return;
+        }
+
+        if self.is_leaf() {
+            for &(position, mass) in self.points.iter() {
+                f(position, mass);
+            }
+            return;
+        }
+
+        let width = self.bottom_right.x - self.top_left.x;
+        let center_of_mass = self.center_of_mass();
+        let distance = point.distance(&center_of_mass);
+        if distance > 0.0 && width / distance < theta {
+            f(center_of_mass, self.total_mass);
+        } else {
+            for child in self.children.iter() {
+                child.for_each_relevant(point, theta, f);
+            }
+        }
+    }
+
+    fn midpoint(&self) -> Vector2D<f64> {
+        (self.top_left + self.bottom_right) / 2.0
+    }
+
+    /// Returns the index of the quadrant containing `position`.
+    fn quadrant_of(&self, position: &Vector2D<f64>) -> usize {
+        let midpoint = self.midpoint();
+        let column = usize::from(position.x >= midpoint.x);
+        let row = usize::from(position.y >= midpoint.y);
+        2 * row + column
+    }
+
+    /// Subdivides the cell into four quadrants and moves its points into them.
+    fn split(&mut self) {
+        let (top_left, bottom_right, midpoint) =
+            (self.top_left, self.bottom_right, self.midpoint());
+        let corners = [
+            (top_left, midpoint),
+            (
+                Vector2D::from_xy(midpoint.x, top_left.y),
+                Vector2D::from_xy(bottom_right.x, midpoint.y),
+            ),
+            (
+                Vector2D::from_xy(top_left.x, midpoint.y),
+                Vector2D::from_xy(midpoint.x, bottom_right.y),
+            ),
+            (midpoint, bottom_right),
+        ];
+        self.children = corners
+            .iter()
+            .map(|&(tl, br)| QuadTree::with_depth(tl, br, self.capacity, self.depth + 1))
+            .collect();
+
+        for (position, mass) in std::mem::take(&mut self.points) {
+            let quadrant = self.quadrant_of(&position);
+            self.children[quadrant].insert(position, mass);
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::assert_vec_approx;
+
+    fn unit_tree(capacity: usize) -> QuadTree {
+        QuadTree::new(
+            Vector2D::from_xy(0.0, 0.0),
+            Vector2D::from_xy(1.0, 1.0),
+            capacity,
+        )
+    }
+
+    #[test]
+    pub fn test_center_of_mass() {
+        let mut tree = unit_tree(1);
+        assert_eq!(tree.center_of_mass(), Vector2D::from_xy(0.5, 0.5));
+
+        tree.insert(Vector2D::from_xy(0.1, 0.1), 1.0);
+        tree.insert(Vector2D::from_xy(0.9, 0.1), 1.0);
+        tree.insert(Vector2D::from_xy(0.9, 0.9), 2.0);
+        assert_eq!(tree.total_mass(), 4.0);
+        assert_vec_approx!(tree.center_of_mass(), Vector2D::from_xy(0.7, 0.5), 1e-12);
+
+        // the bottom right quadrant only holds the heavy point
+        assert_eq!(tree.children[3].total_mass(), 2.0);
+        assert_eq!(
+            tree.children[3].center_of_mass(),
+            Vector2D::from_xy(0.9, 0.9)
+        );
+    }
+
+    #[test]
+    pub fn test_subdivision_depth() {
+        // spread out points need only one split
+        let mut spread = unit_tree(1);
+        for &(x, y) in [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)].iter() {
+            spread.insert(Vector2D::from_xy(x, y), 1.0);
+        }
+        assert_eq!(spread.depth(), 1);
+
+        // a tight cluster forces the tree deeper in one corner only
+        let mut clustered = unit_tree(1);
+        for i in 0..4 {
+            clustered.insert(Vector2D::from_xy(0.01 + 0.001 * i as f64, 0.01), 1.0);
+        }
+        clustered.insert(Vector2D::from_xy(0.9, 0.9), 1.0);
+        assert!(clustered.depth() > 5);
+        assert!(clustered.children[3].is_leaf());
+
+        // coincident points stop splitting at the maximum depth
+        let mut coincident = unit_tree(1);
+        coincident.insert(Vector2D::from_xy(0.3, 0.3), 1.0);
+        coincident.insert(Vector2D::from_xy(0.3, 0.3), 1.0);
+        assert_eq!(coincident.depth(), MAX_DEPTH);
+        assert_eq!(coincident.total_mass(), 2.0);
+    }
+
+    #[test]
+    pub fn test_for_each_relevant() {
+        let mut tree = unit_tree(1);
+        for i in 0..4 {
+            tree.insert(Vector2D::from_xy(0.01 + 0.001 * i as f64, 0.01), 1.0);
+        }
+        tree.insert(Vector2D::from_xy(0.9, 0.9), 1.0);
+        let far = Vector2D::from_xy(100.0, 100.0);
+
+        // theta 0 visits every point
+        let mut visited = vec![];
+        tree.for_each_relevant(&far, 0.0, &mut |position, mass| {
+            visited.push((position, mass))
+        });
+        assert_eq!(visited.len(), 5);
+
+        // from far away, the whole tree acts as one point mass
+        let mut summarized = vec![];
+        tree.for_each_relevant(&far, 0.5, &mut |position, mass| {
+            summarized.push((position, mass))
+        });
+        assert_eq!(summarized.len(), 1);
+        assert_eq!(summarized[0].1, 5.0);
+        assert_vec_approx!(summarized[0].0, tree.center_of_mass(), 1e-12);
+
+        // the total mass seen is the same either way
+        let total: f64 = visited.iter().map(|&(_, mass)| mass).sum();
+        assert_eq!(total, 5.0);
+    }
+}
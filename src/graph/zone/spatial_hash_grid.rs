@@ -0,0 +1,174 @@
+use crate::math::vector_2d::Vector2D;
+use std::collections::HashMap;
+
+/// A uniform grid of square cells, each holding the indices of the nodes inside it. For nodes
+/// spread evenly over the layout this is simpler and faster to build than a
+/// [QuadTree](crate::graph::zone::quad_tree::QuadTree): with cells about as wide as the range of
+/// a force, the only nodes that can act on a node are in its own cell or the eight around it.
+///
+/// Cell `(i, j)` covers `[i * cell_size, (i + 1) * cell_size)` along x, and likewise along y, so a
+/// point on a dividing line belongs to the cell to its right or below it.
+#[derive(Debug, Clone)]
+pub struct SpatialHashGrid {
+    cell_size: f64,
+    cells: HashMap<Vector2D<i32>, Vec<usize>>,
+    node_cells: HashMap<usize, Vector2D<i32>>,
+}
+
+impl SpatialHashGrid {
+    /// Creates an empty grid of cells `cell_size` wide.
+    pub fn new(cell_size: f64) -> Self {
+        SpatialHashGrid {
+            cell_size,
+            cells: HashMap::new(),
+            node_cells: HashMap::new(),
+        }
+    }
+
+    /// Creates a grid of cells `cell_size` wide holding the node at each index of `positions`.
+    pub fn from_positions(positions: &[Vector2D<f64>], cell_size: f64) -> Self {
+        let mut grid = SpatialHashGrid::new(cell_size);
+        for (node_idx, position) in positions.iter().enumerate() {
+            grid.insert(node_idx, position);
+        }
+        grid
+    }
+
+    /// Adds the node at `node_idx` to the cell containing `position`. A node inserted again is
+    /// moved to its new cell.
+    pub fn insert(&mut self, node_idx: usize, position: &Vector2D<f64>) {
+        self.remove(node_idx);
+        let cell = self.cell_of(position);
+        self.cells.entry(cell).or_default().push(node_idx);
+        self.node_cells.insert(node_idx, cell);
+    }
+
+    /// Removes the node at `node_idx` from the grid, if it is in it.
+    pub fn remove(&mut self, node_idx: usize) {
+        let Some(cell) = self.node_cells.remove(&node_idx) else {
+            return;
+        };
+        if let Some(bucket) = self.cells.get_mut(&cell) {
+            bucket.retain(|&idx| idx != node_idx);
+            if bucket.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    /// Returns the coordinates of the cell containing `position`. Positions beyond the range of
+    /// `i32` cells fall in the outermost cell on that side.
+    pub fn cell_of(&self, position: &Vector2D<f64>) -> Vector2D<i32> {
+        Vector2D {
+            x: (position.x / self.cell_size).floor() as i32,
+            y: (position.y / self.cell_size).floor() as i32,
+        }
+    }
+
+    /// Returns the indices, in ascending order, of the other nodes in the same cell as the node
+    /// at `node_idx` or in one of the eight cells around it. These are the candidates for any
+    /// interaction shorter than the cell size. A node not in the grid has no neighbors.
+    pub fn neighbors_of(&self, node_idx: usize) -> Vec<usize> {
+        let Some(cell) = self.node_cells.get(&node_idx) else {
+            return vec![];
+        };
+        let mut neighbors: Vec<usize> = (-1..=1)
+            .flat_map(|dx| (-1..=1).map(move |dy| (dx, dy)))
+            .filter_map(|(dx, dy)| {
+                self.cells.get(&Vector2D {
+                    x: cell.x.saturating_add(dx),
+                    y: cell.y.saturating_add(dy),
+                })
+            })
+            .flatten()
+            .copied()
+            .filter(|&idx| idx != node_idx)
+            .collect();
+        // At the outermost cells the saturated offsets revisit the same cell
+        neighbors.sort_unstable();
+        neighbors.dedup();
+        neighbors
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_cell_of() {
+        let grid = SpatialHashGrid::new(2.0);
+        assert_eq!(
+            grid.cell_of(&Vector2D::from_xy(0.0, 0.0)),
+            Vector2D { x: 0, y: 0 }
+        );
+        assert_eq!(
+            grid.cell_of(&Vector2D::from_xy(3.9, 2.0)),
+            Vector2D { x: 1, y: 1 }
+        );
+        assert_eq!(
+            grid.cell_of(&Vector2D::from_xy(-0.1, -4.0)),
+            Vector2D { x: -1, y: -2 }
+        );
+    }
+
+    #[test]
+    pub fn test_neighbors_of_clustered() {
+        // a tight cluster near the origin and one node far away
+        let positions = vec![
+            Vector2D::from_xy(0.1, 0.1),
+            Vector2D::from_xy(0.9, 0.2),
+            Vector2D::from_xy(-0.5, 0.5),
+            Vector2D::from_xy(0.3, -0.7),
+            Vector2D::from_xy(50.0, 50.0),
+        ];
+        let grid = SpatialHashGrid::from_positions(&positions, 1.0);
+
+        assert_eq!(grid.neighbors_of(0), vec![1, 2, 3]);
+        assert_eq!(grid.neighbors_of(2), vec![0, 1, 3]);
+        assert!(grid.neighbors_of(4).is_empty());
+        assert!(grid.neighbors_of(99).is_empty());
+    }
+
+    #[test]
+    pub fn test_neighbors_of_spread() {
+        // a row of nodes one cell apart sees only the nodes in the adjacent cells
+        let positions: Vec<Vector2D<f64>> = (0..5)
+            .map(|i| Vector2D::from_xy(i as f64 + 0.5, 0.5))
+            .collect();
+        let mut grid = SpatialHashGrid::from_positions(&positions, 1.0);
+
+        assert_eq!(grid.neighbors_of(0), vec![1]);
+        assert_eq!(grid.neighbors_of(2), vec![1, 3]);
+        assert_eq!(grid.neighbors_of(4), vec![3]);
+
+        // moving a node updates the candidates on both sides of the move
+        grid.insert(4, &Vector2D::from_xy(0.5, 1.5));
+        assert_eq!(grid.neighbors_of(4), vec![0, 1]);
+        assert_eq!(grid.neighbors_of(3), vec![2]);
+    }
+
+    #[test]
+    pub fn test_neighbors_of_outermost_cells() {
+        // positions far beyond the range of i32 cells land in the corner cells
+        let positions = vec![
+            Vector2D::from_xy(1e300, 1e300),
+            Vector2D::from_xy(f64::MAX, 1e300),
+            Vector2D::from_xy(-1e300, -1e300),
+            Vector2D::from_xy(-f64::MAX, -1e300),
+        ];
+        let grid = SpatialHashGrid::from_positions(&positions, 1.0);
+        assert_eq!(
+            grid.cell_of(&positions[0]),
+            Vector2D {
+                x: i32::MAX,
+                y: i32::MAX
+            }
+        );
+
+        assert_eq!(grid.neighbors_of(0), vec![1]);
+        assert_eq!(grid.neighbors_of(1), vec![0]);
+        assert_eq!(grid.neighbors_of(2), vec![3]);
+        assert_eq!(grid.neighbors_of(3), vec![2]);
+    }
+}
@@ -4,8 +4,10 @@
 use crate::graph::node::Node;
 use crate::math::vector_2d::Vector2D;
 
+mod error;
 mod graph;
 mod math;
+mod simulation;
 
 fn main() {
     println!("Hello, world!");
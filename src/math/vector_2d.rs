@@ -1,11 +1,32 @@
 use serde::{Deserialize, Serialize};
-use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+use std::ops::{
+    Add, AddAssign, Div, DivAssign, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign,
+};
 
 pub fn approx_equal(a: f64, b: f64, epsilon: f64) -> bool {
     (a - b).abs() < epsilon
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, PartialOrd)]
+/// Asserts that two `Vector2D<f64>`s are equal to within `epsilon` in each component, printing
+/// both vectors if they are not.
+#[cfg(test)]
+#[macro_export]
+macro_rules! assert_vec_approx {
+    ($left:expr, $right:expr, $epsilon:expr) => {{
+        let (left, right) = (&$left, &$right);
+        assert!(
+            left.approx_eq(right, $epsilon),
+            "assertion failed: `left ~= right` (epsilon: {:?})\n  left: {:?}\n right: {:?}",
+            $epsilon,
+            left,
+            right
+        );
+    }};
+}
+
+/// A 2D vector. Vectors with integer components, such as the coordinates of a grid cell, are
+/// `Hash` and can key a `HashMap`; `Vector2D<f64>` is not, since `f64` is neither `Eq` nor `Hash`.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Eq, PartialEq, PartialOrd, Hash)]
 pub struct Vector2D<T> {
     pub x: T,
     pub y: T,
@@ -18,7 +39,8 @@ impl<
             + Mul<Output = T>
             + Div<Output = T>
             + Neg<Output = T>
-            + From<f64>,
+            + From<f64>
+            + PartialEq,
     > Vector2D<T>
 {
     pub fn from_xy(x: T, y: T) -> Self {
@@ -43,6 +65,13 @@ impl<
         self.x * other.x + self.y * other.y
     }
 
+    /// The z component of the 3D cross product of the two vectors, `x1 * y2 - y1 * x2`. It is
+    /// positive when `other` is counterclockwise from the vector, negative when clockwise, and 0
+    /// when they are parallel.
+    pub fn cross(&self, other: &Vector2D<T>) -> T {
+        self.x * other.y - self.y * other.x
+    }
+
     pub fn scale(&self, scalar: T) -> Vector2D<T> {
         Vector2D {
             x: self.x * scalar,
@@ -57,10 +86,45 @@ impl<
         }
     }
 
+    /// Rotates the vector a quarter turn counterclockwise. Unlike `rotate(PI / 2.0)`, this only
+    /// swaps and negates components, so it is exact.
+    pub fn rotate90(&self) -> Vector2D<T> {
+        self.orthogonal()
+    }
+
+    /// Rotates the vector a half turn, exactly.
+    pub fn rotate180(&self) -> Vector2D<T> {
+        Vector2D {
+            x: -self.x,
+            y: -self.y,
+        }
+    }
+
+    /// Rotates the vector three quarter turns counterclockwise, or one clockwise, exactly.
+    pub fn rotate270(&self) -> Vector2D<T> {
+        Vector2D {
+            x: self.y,
+            y: -self.x,
+        }
+    }
+
+    /// Projects the vector onto `other`. Projecting onto the zero vector has no well-defined
+    /// direction, so it returns the zero vector rather than dividing by zero.
     pub fn project_on(&self, other: &Vector2D<T>) -> Vector2D<T> {
-        let scalar = self.dot(other) / other.dot(other);
+        let denominator = other.dot(other);
+        if denominator == T::from(0.0) {
+            return Vector2D::from_xy(T::from(0.0), T::from(0.0));
+        }
+        let scalar = self.dot(other) / denominator;
         other.scale(scalar)
     }
+
+    /// Returns the component of the vector orthogonal to `other`, so that
+    /// `self.project_on(other) + self.reject_from(other) == self`. Rejecting from the zero vector
+    /// returns the vector unchanged.
+    pub fn reject_from(&self, other: &Vector2D<T>) -> Vector2D<T> {
+        self.sub(&self.project_on(other))
+    }
 }
 impl Vector2D<f64> {
     pub fn from_rtheta(radius: f64, angle: f64) -> Vector2D<f64> {
@@ -95,20 +159,133 @@ impl Vector2D<f64> {
         }
     }
 
+    /// Same as [Vector2D::rotate_around](Vector2D::rotate_around), with the angle in degrees.
+    pub fn rotate_around_degrees(&self, angle: f64, other: &Vector2D<f64>) -> Vector2D<f64> {
+        self.rotate_around(angle.to_radians(), other)
+    }
+
     pub fn magnitude(&self) -> f64 {
         (self.x * self.x + self.y * self.y).sqrt()
     }
 
+    /// Magnitude of the vector computed with `hypot`, which avoids the intermediate overflow of
+    /// `x * x + y * y` for very large components. For example, the magnitude of `(1e200, 1e200)`
+    /// is representable, but [Vector2D::magnitude](Vector2D::magnitude) returns infinity for it.
+    pub fn magnitude_stable(&self) -> f64 {
+        self.x.hypot(self.y)
+    }
+
     pub fn angle(&self) -> f64 {
         self.y.atan2(self.x)
     }
 
+    /// Signed angle of the turn from this vector to `other`, in `(-PI, PI]`: positive when the
+    /// shorter turn is counter-clockwise and negative when it is clockwise. Opposite vectors give
+    /// `PI`, and the angle to or from a zero vector is 0.
+    pub fn angle_to(&self, other: &Vector2D<f64>) -> f64 {
+        let angle = self.cross(other).atan2(self.dot(other));
+        if angle == -std::f64::consts::PI {
+            std::f64::consts::PI
+        } else {
+            angle
+        }
+    }
+
+    /// Decompose the vector into its polar coordinates `(radius, angle)`, the inverse of
+    /// [Vector2D::from_rtheta](Vector2D::from_rtheta). The radius is computed as in
+    /// [Vector2D::magnitude_stable](Vector2D::magnitude_stable).
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_polar(&self) -> (f64, f64) {
+        (self.magnitude_stable(), self.angle())
+    }
+
     pub fn distance(&self, other: &Vector2D<f64>) -> f64 {
-        (self.sub(other)).magnitude()
+        (self.sub(other)).magnitude_stable()
     }
 
+    /// Manhattan (L1) distance to `other`: the sum of the absolute componentwise differences.
+    ///
+    /// ### See also
+    ///
+    /// [Vector2D::distance](Vector2D::distance) for the Euclidean distance
+    pub fn manhattan_distance(&self, other: &Vector2D<f64>) -> f64 {
+        let delta = self.sub(other);
+        delta.x.abs() + delta.y.abs()
+    }
+
+    /// Shortest distance from the point to the line segment from `a` to `b`. The projection of
+    /// the point onto the segment is clamped to the endpoints, so points beyond either end are
+    /// measured to the nearest endpoint. A degenerate segment (`a == b`) is treated as a point.
+    pub fn distance_to_segment(&self, a: &Vector2D<f64>, b: &Vector2D<f64>) -> f64 {
+        self.distance(&a.lerp(b, self.project_onto_segment(a, b)))
+    }
+
+    /// Returns the parameter `t` in `[0, 1]` of the point `a + t * (b - a)` on the line segment
+    /// from `a` to `b` nearest to this point. A degenerate segment (`a == b`) gives 0.
+    pub fn project_onto_segment(&self, a: &Vector2D<f64>, b: &Vector2D<f64>) -> f64 {
+        let segment = b.sub(a);
+        let length_squared = segment.dot(&segment);
+        if length_squared == 0.0 {
+            return 0.0;
+        }
+        (self.sub(a).dot(&segment) / length_squared).clamp(0.0, 1.0)
+    }
+
+    /// Returns the parameters `(s, t)` of the nearest points `a1 + s * (a2 - a1)` and
+    /// `b1 + t * (b2 - b1)` on the segments from `a1` to `a2` and from `b1` to `b2`. For segments
+    /// that cross these locate the crossing; otherwise one of the nearest points is an endpoint.
+    pub fn closest_points_on_segments(
+        a1: &Vector2D<f64>,
+        a2: &Vector2D<f64>,
+        b1: &Vector2D<f64>,
+        b2: &Vector2D<f64>,
+    ) -> (f64, f64) {
+        if Vector2D::segments_cross(a1, a2, b1, b2) {
+            let r = a2.sub(a1);
+            let q = b2.sub(b1);
+            let offset = b1.sub(a1);
+            let denominator = r.cross(&q);
+            return (
+                offset.cross(&q) / denominator,
+                offset.cross(&r) / denominator,
+            );
+        }
+
+        // Otherwise the gap is narrowest at one of the four endpoints
+        let candidates = [
+            (0.0, a1.project_onto_segment(b1, b2)),
+            (1.0, a2.project_onto_segment(b1, b2)),
+            (b1.project_onto_segment(a1, a2), 0.0),
+            (b2.project_onto_segment(a1, a2), 1.0),
+        ];
+        let gap = |&(s, t): &(f64, f64)| a1.lerp(a2, s).distance(&b1.lerp(b2, t));
+        candidates
+            .into_iter()
+            .min_by(|p, q| gap(p).total_cmp(&gap(q)))
+            .unwrap()
+    }
+
+    /// Boolean to test if the segment from `a1` to `a2` properly crosses the segment from `b1` to
+    /// `b2`, i.e. they meet at a single point interior to both. Segments that only touch, at an
+    /// endpoint or by overlapping along a line, do not cross.
+    pub fn segments_cross(
+        a1: &Vector2D<f64>,
+        a2: &Vector2D<f64>,
+        b1: &Vector2D<f64>,
+        b2: &Vector2D<f64>,
+    ) -> bool {
+        // Each segment's endpoints must lie strictly on opposite sides of the other segment
+        let side =
+            |p: &Vector2D<f64>, q: &Vector2D<f64>, r: &Vector2D<f64>| q.sub(p).cross(&r.sub(p));
+        let straddles = |s1: f64, s2: f64| (s1 > 0.0 && s2 < 0.0) || (s1 < 0.0 && s2 > 0.0);
+        straddles(side(a1, a2, b1), side(a1, a2, b2))
+            && straddles(side(b1, b2, a1), side(b1, b2, a2))
+    }
+
+    /// Returns the unit vector in the same direction. The zero vector has no direction, so it
+    /// normalizes to the zero vector.
     pub fn normalize(&self) -> Vector2D<f64> {
-        let magnitude = self.magnitude();
+        let magnitude = self.magnitude_stable();
         if magnitude == 0.0 {
             return Vector2D { x: 0.0, y: 0.0 };
         }
@@ -127,10 +304,186 @@ impl Vector2D<f64> {
         start.scale(1.0 - t) + end.scale(t)
     }
 
+    /// Method form of [Vector2D::linear_interpolation](Vector2D::linear_interpolation), moving
+    /// from `self` toward `other`.
+    pub fn lerp(&self, other: &Vector2D<f64>, t: f64) -> Vector2D<f64> {
+        Vector2D::linear_interpolation(self, other, t)
+    }
+
+    /// Same as [Vector2D::lerp](Vector2D::lerp) with `t` clamped to `[0, 1]`, so it never
+    /// overshoots either endpoint.
+    pub fn lerp_clamped(&self, other: &Vector2D<f64>, t: f64) -> Vector2D<f64> {
+        self.lerp(other, t.clamp(0.0, 1.0))
+    }
+
+    /// Interpolate from `self` toward `other` with smoothstep easing: `t` is clamped to `[0, 1]`
+    /// and mapped through `3t^2 - 2t^3`, which starts and ends with zero speed. Animating node
+    /// positions with this avoids the abrupt starts and stops of a linear interpolation.
+    pub fn smoothstep(&self, other: &Vector2D<f64>, t: f64) -> Vector2D<f64> {
+        let t = t.clamp(0.0, 1.0);
+        self.lerp(other, t * t * (3.0 - 2.0 * t))
+    }
+
+    /// Interpolate between two vectors along an arc, interpolating the angle and the magnitude
+    /// separately. The angle is interpolated along the shorter way around the circle.
+    /// ### Parameters
+    /// - `start`: The vector at `t = 0`
+    /// - `end`: The vector at `t = 1`
+    /// - `t`: The interpolation parameter
+    /// ### Notes
+    /// - If either endpoint has zero magnitude its angle is undefined, so this falls back to
+    ///   [Vector2D::linear_interpolation](Vector2D::linear_interpolation)
+    pub fn slerp(start: &Vector2D<f64>, end: &Vector2D<f64>, t: f64) -> Vector2D<f64> {
+        let start_magnitude = start.magnitude();
+        let end_magnitude = end.magnitude();
+        if start_magnitude == 0.0 || end_magnitude == 0.0 {
+            return Vector2D::linear_interpolation(start, end, t);
+        }
+
+        // Wrap the angle difference into [-PI, PI] so we take the shorter arc
+        let tau = 2.0 * std::f64::consts::PI;
+        let mut delta = (end.angle() - start.angle()) % tau;
+        if delta > std::f64::consts::PI {
+            delta -= tau;
+        } else if delta < -std::f64::consts::PI {
+            delta += tau;
+        }
+
+        let angle = start.angle() + t * delta;
+        let magnitude = start_magnitude + t * (end_magnitude - start_magnitude);
+        Vector2D::from_rtheta(magnitude, angle)
+    }
+
     pub fn relative_to(&self, other: &Vector2D<f64>) -> Vector2D<f64> {
         self.sub(other)
     }
 
+    /// Boolean to test if both components are within `epsilon` of the corresponding components
+    /// of `other`.
+    pub fn approx_eq(&self, other: &Vector2D<f64>, epsilon: f64) -> bool {
+        approx_equal(self.x, other.x, epsilon) && approx_equal(self.y, other.y, epsilon)
+    }
+
+    /// Linearly map the point **componentwise** from the box with corners `from` to the box with
+    /// corners `to`, e.g. from world coordinates to screen coordinates. The corners are matched in
+    /// order, so flipping an axis is a matter of swapping that axis in one of the boxes. If the
+    /// `from` box has zero extent along an axis, that component maps to the middle of `to`.
+    ///
+    /// ### Examples
+    /// ```
+    /// use crate::math::vector_2d::Vector2D;
+    /// let world = (Vector2D::from_xy(-1.0, -1.0), Vector2D::from_xy(1.0, 1.0));
+    /// let screen = (Vector2D::from_xy(0.0, 600.0), Vector2D::from_xy(800.0, 0.0));
+    /// let pixel = Vector2D::from_xy(0.0, 0.5).remap(world, screen);
+    /// ```
+    pub fn remap(
+        &self,
+        from: (Vector2D<f64>, Vector2D<f64>),
+        to: (Vector2D<f64>, Vector2D<f64>),
+    ) -> Vector2D<f64> {
+        let remap_axis = |value: f64, from_lo: f64, from_hi: f64, to_lo: f64, to_hi: f64| {
+            let t = if from_hi == from_lo {
+                0.5
+            } else {
+                (value - from_lo) / (from_hi - from_lo)
+            };
+            to_lo + t * (to_hi - to_lo)
+        };
+        Vector2D {
+            x: remap_axis(self.x, from.0.x, from.1.x, to.0.x, to.1.x),
+            y: remap_axis(self.y, from.0.y, from.1.y, to.0.y, to.1.y),
+        }
+    }
+
+    /// Boolean to test if neither component is infinite or NaN.
+    pub fn is_finite(&self) -> bool {
+        self.x.is_finite() && self.y.is_finite()
+    }
+
+    /// Replace each infinite or NaN component with the corresponding component of `fallback`,
+    /// leaving finite components unchanged.
+    pub fn sanitize(&self, fallback: Vector2D<f64>) -> Vector2D<f64> {
+        Vector2D {
+            x: if self.x.is_finite() {
+                self.x
+            } else {
+                fallback.x
+            },
+            y: if self.y.is_finite() {
+                self.y
+            } else {
+                fallback.y
+            },
+        }
+    }
+
+    /// Clamp the vector **componentwise** so that each component lies between the corresponding
+    /// components of `min` and `max`.
+    pub fn clamp(&self, min: &Vector2D<f64>, max: &Vector2D<f64>) -> Vector2D<f64> {
+        Vector2D {
+            x: self.x.clamp(min.x, max.x),
+            y: self.y.clamp(min.y, max.y),
+        }
+    }
+
+    /// Reflect the vector across the line perpendicular to `normal`, as a ball bouncing off a wall
+    /// with that normal would. `normal` does not need to be a unit vector. Reflecting across the
+    /// zero vector leaves the vector unchanged.
+    pub fn reflect(&self, normal: &Vector2D<f64>) -> Vector2D<f64> {
+        let n = normal.normalize();
+        self.sub(&n.scale(2.0 * self.dot(&n)))
+    }
+
+    /// Absolute value of the vector, taken **componentwise**.
+    pub fn abs(&self) -> Vector2D<f64> {
+        Vector2D {
+            x: self.x.abs(),
+            y: self.y.abs(),
+        }
+    }
+
+    /// Sign of the vector, taken **componentwise**: -1 for negative components, 1 for positive
+    /// components and 0 for zero components. Unlike `f64::signum`, both +0.0 and -0.0 map to 0.
+    pub fn signum(&self) -> Vector2D<f64> {
+        let sign = |value: f64| {
+            if value == 0.0 {
+                0.0
+            } else {
+                value.signum()
+            }
+        };
+        Vector2D {
+            x: sign(self.x),
+            y: sign(self.y),
+        }
+    }
+
+    /// Product of the vectors taken **componentwise** (the Hadamard product), e.g. to scale x and
+    /// y by different factors. Multiplying two vectors with `*` gives their dot product instead.
+    pub fn hadamard(&self, other: &Vector2D<f64>) -> Vector2D<f64> {
+        Vector2D {
+            x: self.x * other.x,
+            y: self.y * other.y,
+        }
+    }
+
+    /// Quotient of the vectors taken **componentwise**, undoing
+    /// [Vector2D::hadamard](Vector2D::hadamard). A component divided by zero is 0 rather than
+    /// infinite or NaN.
+    pub fn hadamard_div(&self, other: &Vector2D<f64>) -> Vector2D<f64> {
+        let divide = |value: f64, divisor: f64| {
+            if divisor == 0.0 {
+                0.0
+            } else {
+                value / divisor
+            }
+        };
+        Vector2D {
+            x: divide(self.x, other.x),
+            y: divide(self.y, other.y),
+        }
+    }
+
     /// Round the vector **componentwise** to the nearest n decimal places
     /// ### Parameters
     /// - `n`: The number of decimal places to round to
@@ -156,12 +509,30 @@ impl Vector2D<f64> {
     pub fn round(&self, n: usize) -> Vector2D<f64> {
         let order_of_mag: f64 = 10.0_f64.powi(n as i32);
         Vector2D {
-            x: order_of_mag * self.x.round() / order_of_mag,
-            y: order_of_mag * self.y.round() / order_of_mag,
+            x: (order_of_mag * self.x).round() / order_of_mag,
+            y: (order_of_mag * self.y).round() / order_of_mag,
         }
     }
 }
 
+/// Measurements for vectors of any component type that converts losslessly to `f64`, such as
+/// the `Vector2D<i32>` coordinates of grid cells.
+impl<T: Into<f64> + Copy> Vector2D<T> {
+    /// Length of the vector, with the components converted to `f64` first.
+    pub fn magnitude_f64(&self) -> f64 {
+        let (x, y): (f64, f64) = (self.x.into(), self.y.into());
+        x.hypot(y)
+    }
+
+    /// Distance to `other`, with the components converted to `f64` first so the subtraction
+    /// cannot overflow.
+    pub fn distance_f64(&self, other: &Vector2D<T>) -> f64 {
+        let (x1, y1): (f64, f64) = (self.x.into(), self.y.into());
+        let (x2, y2): (f64, f64) = (other.x.into(), other.y.into());
+        (x1 - x2).hypot(y1 - y2)
+    }
+}
+
 impl<
         T: Copy
             + Add<Output = T>
@@ -203,14 +574,12 @@ impl<
 }
 
 impl<
-        'a,
-        'b,
         T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Neg<Output = T> + From<f64>,
-    > Add<&'b Vector2D<T>> for &'a Vector2D<T>
+    > Add<&Vector2D<T>> for &Vector2D<T>
 {
     type Output = Vector2D<T>;
 
-    fn add(self, other: &'b Vector2D<T>) -> Vector2D<T> {
+    fn add(self, other: &Vector2D<T>) -> Vector2D<T> {
         Vector2D {
             x: self.x + other.x,
             y: self.y + other.y,
@@ -219,14 +588,12 @@ impl<
 }
 
 impl<
-        'a,
-        'b,
         T: Copy + Add<Output = T> + Sub<Output = T> + Mul<Output = T> + Neg<Output = T> + From<f64>,
-    > Sub<&'b Vector2D<T>> for &'a Vector2D<T>
+    > Sub<&Vector2D<T>> for &Vector2D<T>
 {
     type Output = Vector2D<T>;
 
-    fn sub(self, other: &'b Vector2D<T>) -> Vector2D<T> {
+    fn sub(self, other: &Vector2D<T>) -> Vector2D<T> {
         Vector2D {
             x: self.x - other.x,
             y: self.y - other.y,
@@ -275,7 +642,7 @@ where
     }
 }
 
-impl<'a, T> Div<T> for &'a Vector2D<T>
+impl<T> Div<T> for &Vector2D<T>
 where
     T: Div<Output = T> + Copy,
 {
@@ -303,7 +670,7 @@ where
     }
 }
 
-impl<'a, T> Neg for &'a Vector2D<T>
+impl<T> Neg for &Vector2D<T>
 where
     T: Mul<Output = T> + Copy + From<f64>,
 {
@@ -345,9 +712,40 @@ impl<T: Div<Output = T> + Copy> DivAssign<T> for Vector2D<T> {
     }
 }
 
+/// Index the components of the vector as a 2-element container, so that `v[0]` is `x` and `v[1]`
+/// is `y`. Any other index panics.
+impl<T> Index<usize> for Vector2D<T> {
+    type Output = T;
+
+    fn index(&self, index: usize) -> &T {
+        match index {
+            0 => &self.x,
+            1 => &self.y,
+            _ => panic!(
+                "Index {} out of range for Vector2D (expected 0 or 1)",
+                index
+            ),
+        }
+    }
+}
+
+impl<T> IndexMut<usize> for Vector2D<T> {
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        match index {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            _ => panic!(
+                "Index {} out of range for Vector2D (expected 0 or 1)",
+                index
+            ),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::assert_vec_approx;
     use crate::math::vector_2d::approx_equal;
     pub const PI: f64 = std::f64::consts::PI;
 
@@ -373,6 +771,34 @@ mod tests {
         assert_eq!(v5.y, 6.0);
     }
 
+    #[test]
+    fn test_magnitude_f64() {
+        assert_eq!(Vector2D { x: 3, y: 4 }.magnitude_f64(), 5.0);
+        assert_eq!(Vector2D { x: -3_i32, y: 0 }.magnitude_f64(), 3.0);
+        assert_eq!(
+            Vector2D { x: 1_u8, y: 2 }.distance_f64(&Vector2D { x: 4, y: 6 }),
+            5.0
+        );
+        assert_eq!(
+            Vector2D { x: i32::MIN, y: 0 }.distance_f64(&Vector2D { x: i32::MAX, y: 0 }),
+            u32::MAX as f64
+        );
+        assert_eq!(Vector2D::from_xy(0.6, 0.8).magnitude_f64(), 1.0);
+    }
+
+    #[test]
+    fn test_hash_integer_vectors() {
+        use std::collections::HashSet;
+
+        let cells: HashSet<Vector2D<i32>> = [(0, 0), (1, -2), (0, 0), (-3, 4), (1, -2)]
+            .iter()
+            .map(|&(x, y)| Vector2D { x, y })
+            .collect();
+        assert_eq!(cells.len(), 3);
+        assert!(cells.contains(&Vector2D { x: 1, y: -2 }));
+        assert!(!cells.contains(&Vector2D { x: -2, y: 1 }));
+    }
+
     #[test]
     fn test_sub() {
         let v1 = Vector2D::from_xy(1.0, 2.0);
@@ -426,6 +852,26 @@ mod tests {
         assert_eq!(magnitude, 5.0);
     }
 
+    #[test]
+    fn test_magnitude_stable() {
+        let v1 = Vector2D::from_xy(3.0, 4.0);
+        assert_eq!(v1.magnitude_stable(), 5.0);
+
+        // the naive magnitude overflows, but the true magnitude is representable
+        let v2 = Vector2D::from_xy(1e200, 1e200);
+        assert!(v2.magnitude().is_infinite());
+        assert!(v2.magnitude_stable().is_finite());
+        assert!(approx_equal(
+            v2.magnitude_stable() / 1e200,
+            2.0_f64.sqrt(),
+            1e-10
+        ));
+
+        // normalize and distance use the stable magnitude
+        assert!(approx_equal(v2.normalize().magnitude(), 1.0, 1e-10));
+        assert!(v2.distance(&Vector2D::from_xy(0.0, 0.0)).is_finite());
+    }
+
     #[test]
     fn test_angle() {
         let v1 = Vector2D::from_xy(1.0, 1.0);
@@ -433,6 +879,34 @@ mod tests {
         assert_eq!(angle, PI / 4.0);
     }
 
+    #[test]
+    fn test_angle_to() {
+        let x = Vector2D::from_xy(1.0, 0.0);
+        assert_eq!(x.angle_to(&Vector2D::from_xy(0.0, 1.0)), PI / 2.0);
+        assert_eq!(x.angle_to(&Vector2D::from_xy(0.0, -1.0)), -PI / 2.0);
+        assert_eq!(x.angle_to(&Vector2D::from_xy(-1.0, 0.0)), PI);
+        assert_eq!(x.angle_to(&Vector2D::from_xy(-1.0, -0.0)), PI);
+        assert_eq!(x.angle_to(&Vector2D::from_xy(3.0, 0.0)), 0.0);
+
+        // the angle is relative, so rotating both vectors leaves it unchanged
+        let a = Vector2D::from_xy(2.0, 1.0);
+        let b = Vector2D::from_xy(-1.0, 2.5);
+        assert!(approx_equal(
+            a.rotate(1.0).angle_to(&b.rotate(1.0)),
+            a.angle_to(&b),
+            1e-12
+        ));
+        assert!(approx_equal(b.angle_to(&a), -a.angle_to(&b), 1e-12));
+    }
+
+    #[test]
+    fn test_round() {
+        let v1 = Vector2D::from_xy(1.234, -2.345678);
+        assert_eq!(v1.round(2), Vector2D::from_xy(1.23, -2.35));
+        assert_eq!(v1.round(0), Vector2D::from_xy(1.0, -2.0));
+        assert_ne!(v1.round(5), v1.round(0));
+    }
+
     #[test]
     fn test_normalize() {
         let v1 = Vector2D::from_xy(3.0, 4.0);
@@ -440,6 +914,13 @@ mod tests {
         assert!(approx_equal(v2.x * v2.x + v2.y * v2.y, 1.0, 1e-4));
     }
 
+    #[test]
+    fn test_normalize_zero() {
+        let v1 = Vector2D::from_xy(0.0, 0.0);
+        assert_eq!(v1.normalize(), Vector2D::from_xy(0.0, 0.0));
+        assert_eq!(v1.orthonormal(), Vector2D::from_xy(0.0, 0.0));
+    }
+
     #[test]
     fn test_orthogonal() {
         let v1 = Vector2D::from_xy(1.0, 2.0);
@@ -463,6 +944,92 @@ mod tests {
         assert_eq!(v3.y, 1.76);
     }
 
+    #[test]
+    fn test_project_on_zero() {
+        let v1: Vector2D<f64> = Vector2D::from_xy(1.0, 2.0);
+        let v2 = Vector2D::from_xy(0.0, 0.0);
+        let v3 = v1.project_on(&v2);
+        assert_eq!(v3, Vector2D::from_xy(0.0, 0.0));
+        assert!(v3.x.is_finite() && v3.y.is_finite());
+    }
+
+    #[test]
+    fn test_reject_from() {
+        let v1 = Vector2D::from_xy(1.0, 2.0);
+        let v2 = Vector2D::from_xy(3.0, 4.0);
+        let rejection = v1.reject_from(&v2);
+
+        // the projection and rejection add back up to the vector
+        assert_vec_approx!(v1.project_on(&v2) + rejection, v1, 1e-12);
+
+        // the rejection is orthogonal to the other vector
+        assert!(approx_equal(rejection.dot(&v2), 0.0, 1e-12));
+
+        assert_eq!(v1.reject_from(&Vector2D::from_xy(0.0, 0.0)), v1);
+        assert_eq!(
+            v1.reject_from(&Vector2D::from_xy(5.0, 0.0)),
+            Vector2D::from_xy(0.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn test_remap() {
+        let world = (Vector2D::from_xy(-1.0, -1.0), Vector2D::from_xy(1.0, 1.0));
+
+        // screen y grows downward, so the world's bottom edge maps to the screen's largest y
+        let screen = (Vector2D::from_xy(0.0, 600.0), Vector2D::from_xy(800.0, 0.0));
+        assert_eq!(world.0.remap(world, screen), Vector2D::from_xy(0.0, 600.0));
+        assert_eq!(world.1.remap(world, screen), Vector2D::from_xy(800.0, 0.0));
+        assert_eq!(
+            Vector2D::from_xy(0.0, 0.0).remap(world, screen),
+            Vector2D::from_xy(400.0, 300.0)
+        );
+        assert_eq!(
+            Vector2D::from_xy(-1.0, 1.0).remap(world, screen),
+            Vector2D::from_xy(0.0, 0.0)
+        );
+        assert_eq!(
+            Vector2D::from_xy(0.5, 0.5).remap(world, screen),
+            Vector2D::from_xy(600.0, 150.0)
+        );
+
+        // a box with no height maps every y to the middle of the target
+        let flat = (Vector2D::from_xy(0.0, 2.0), Vector2D::from_xy(4.0, 2.0));
+        assert_eq!(
+            Vector2D::from_xy(1.0, 2.0).remap(flat, screen),
+            Vector2D::from_xy(200.0, 300.0)
+        );
+    }
+
+    #[test]
+    fn test_is_finite() {
+        assert!(Vector2D::from_xy(1.0, -2.0).is_finite());
+        assert!(!Vector2D::from_xy(f64::INFINITY, 0.0).is_finite());
+        assert!(!Vector2D::from_xy(0.0, f64::NEG_INFINITY).is_finite());
+        assert!(!Vector2D::from_xy(f64::NAN, 0.0).is_finite());
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let fallback = Vector2D::from_xy(7.0, 8.0);
+        assert_eq!(
+            Vector2D::from_xy(1.0, 2.0).sanitize(fallback),
+            Vector2D::from_xy(1.0, 2.0)
+        );
+        assert_eq!(
+            Vector2D::from_xy(f64::NAN, 2.0).sanitize(fallback),
+            Vector2D::from_xy(7.0, 2.0)
+        );
+        assert_eq!(
+            Vector2D::from_xy(1.0, f64::INFINITY).sanitize(fallback),
+            Vector2D::from_xy(1.0, 8.0)
+        );
+        assert_eq!(
+            Vector2D::from_xy(f64::NEG_INFINITY, f64::NAN).sanitize(fallback),
+            fallback
+        );
+    }
+
     #[test]
     fn test_rotation() {
         let epsilon = 1e-10; // Define an appropriate tolerance level
@@ -476,6 +1043,21 @@ mod tests {
         assert!(approx_equal(rotated.y, v1.y, epsilon));
     }
 
+    #[test]
+    fn test_rotate_quarter_turns() {
+        let v = Vector2D::from_xy(1.0, 0.0);
+        assert_eq!(v.rotate90(), Vector2D::from_xy(0.0, 1.0));
+        assert_eq!(v.rotate180(), Vector2D::from_xy(-1.0, 0.0));
+        assert_eq!(v.rotate270(), Vector2D::from_xy(0.0, -1.0));
+
+        // four quarter turns come back exactly, where rotate drifts
+        let w = Vector2D::from_xy(0.3, -1.7);
+        assert_eq!(w.rotate90().rotate90().rotate90().rotate90(), w);
+        assert_eq!(w.rotate90().rotate90(), w.rotate180());
+        assert_eq!(w.rotate180().rotate90(), w.rotate270());
+        assert_vec_approx!(w.rotate90(), w.rotate(PI / 2.0), 1e-12);
+    }
+
     #[test]
     fn test_rotate_around() {
         let v1 = Vector2D::from_xy(1.0, 0.0);
@@ -486,6 +1068,22 @@ mod tests {
         assert!(approx_equal(v3.y, 1.0, 1e-10));
     }
 
+    #[test]
+    fn test_rotate_around_degrees() {
+        let v1 = Vector2D::from_xy(3.0, 1.0);
+        let pivot = Vector2D::from_xy(1.0, 1.0);
+        assert_vec_approx!(
+            v1.rotate_around_degrees(90.0, &pivot),
+            Vector2D::from_xy(1.0, 3.0),
+            1e-10
+        );
+        assert_vec_approx!(
+            v1.rotate_around_degrees(-180.0, &pivot),
+            v1.rotate_around(-PI, &pivot),
+            1e-10
+        );
+    }
+
     #[test]
     fn test_distance() {
         let v1 = Vector2D::from_xy(1.0, 0.0);
@@ -503,6 +1101,58 @@ mod tests {
         assert_eq!(v3.y, 0.0);
     }
 
+    #[test]
+    fn test_lerp() {
+        let v1 = Vector2D::from_xy(1.0, 0.0);
+        let v2 = Vector2D::from_xy(3.0, 2.0);
+        let v3 = v1.lerp(&v2, 0.5);
+        assert_eq!(v3, Vector2D::linear_interpolation(&v1, &v2, 0.5));
+        assert_eq!(v3.x, 2.0);
+        assert_eq!(v3.y, 1.0);
+    }
+
+    #[test]
+    fn test_lerp_clamped_and_smoothstep() {
+        let v1 = Vector2D::from_xy(1.0, 0.0);
+        let v2 = Vector2D::from_xy(3.0, 2.0);
+        assert_eq!(v1.lerp_clamped(&v2, -0.5), v1);
+        assert_eq!(v1.lerp_clamped(&v2, 1.5), v2);
+        assert_eq!(v1.lerp_clamped(&v2, 0.25), v1.lerp(&v2, 0.25));
+
+        assert_eq!(v1.smoothstep(&v2, -1.0), v1);
+        assert_eq!(v1.smoothstep(&v2, 2.0), v2);
+        assert_eq!(v1.smoothstep(&v2, 0.5), Vector2D::from_xy(2.0, 1.0));
+
+        // eased: behind a linear interpolation early on and ahead of it late
+        assert!(v1.smoothstep(&v2, 0.1).x < v1.lerp(&v2, 0.1).x);
+        assert!(v1.smoothstep(&v2, 0.9).x > v1.lerp(&v2, 0.9).x);
+    }
+
+    #[test]
+    fn test_slerp() {
+        let v1 = Vector2D::from_xy(1.0, 0.0);
+        let v2 = Vector2D::from_xy(0.0, 1.0);
+
+        // halfway between perpendicular unit vectors is the 45 degree unit vector
+        let v3 = Vector2D::slerp(&v1, &v2, 0.5);
+        assert!(approx_equal(v3.magnitude(), 1.0, 1e-10));
+        assert!(approx_equal(v3.angle(), PI / 4.0, 1e-10));
+
+        // endpoints are reproduced
+        let start = Vector2D::slerp(&v1, &v2, 0.0);
+        let end = Vector2D::slerp(&v1, &v2, 1.0);
+        assert!(approx_equal(start.x, 1.0, 1e-10) && approx_equal(start.y, 0.0, 1e-10));
+        assert!(approx_equal(end.x, 0.0, 1e-10) && approx_equal(end.y, 1.0, 1e-10));
+    }
+
+    #[test]
+    fn test_slerp_zero_magnitude() {
+        let v1 = Vector2D::from_xy(0.0, 0.0);
+        let v2 = Vector2D::from_xy(2.0, 0.0);
+        let v3 = Vector2D::slerp(&v1, &v2, 0.5);
+        assert_eq!(v3, Vector2D::from_xy(1.0, 0.0));
+    }
+
     #[test]
     fn test_from_theta() {
         let v1 = Vector2D::from_theta(PI / 2.0);
@@ -517,6 +1167,23 @@ mod tests {
         assert!(approx_equal(v1.y, 2.0, 1e-4));
     }
 
+    #[test]
+    fn test_to_polar() {
+        let (radius, angle) = Vector2D::from_xy(0.0, 2.0).to_polar();
+        assert_eq!(radius, 2.0);
+        assert_eq!(angle, PI / 2.0);
+
+        for v in [
+            Vector2D::from_xy(3.0, -4.0),
+            Vector2D::from_xy(-1.5, 0.25),
+            Vector2D::from_xy(0.0, 0.0),
+        ] {
+            let (radius, angle) = v.to_polar();
+            let round_trip = Vector2D::from_rtheta(radius, angle);
+            assert!(round_trip.approx_eq(&v, 1e-12));
+        }
+    }
+
     #[test]
     fn test_relative_to() {
         let v1 = Vector2D::from_xy(1.0, 2.0);
@@ -525,4 +1192,209 @@ mod tests {
         assert_eq!(v3.x, -2.0);
         assert_eq!(v3.y, -2.0);
     }
+
+    #[test]
+    fn test_clamp() {
+        let min = Vector2D::from_xy(0.0, 0.0);
+        let max = Vector2D::from_xy(2.0, 2.0);
+        assert_eq!(
+            Vector2D::from_xy(1.0, 1.0).clamp(&min, &max),
+            Vector2D::from_xy(1.0, 1.0)
+        );
+        assert_eq!(
+            Vector2D::from_xy(-1.0, 3.0).clamp(&min, &max),
+            Vector2D::from_xy(0.0, 2.0)
+        );
+    }
+
+    #[test]
+    fn test_reflect() {
+        let v1 = Vector2D::from_xy(1.0, -2.0);
+
+        // bouncing off a vertical wall flips x, and off a horizontal wall flips y
+        assert_eq!(
+            v1.reflect(&Vector2D::from_xy(-3.0, 0.0)),
+            Vector2D::from_xy(-1.0, -2.0)
+        );
+        assert_eq!(
+            v1.reflect(&Vector2D::from_xy(0.0, 1.0)),
+            Vector2D::from_xy(1.0, 2.0)
+        );
+        assert_eq!(v1.reflect(&Vector2D::from_xy(0.0, 0.0)), v1);
+    }
+
+    #[test]
+    fn test_manhattan_distance() {
+        let v1 = Vector2D::from_xy(1.0, 2.0);
+        let v2 = Vector2D::from_xy(4.0, -2.0);
+        assert_eq!(v1.manhattan_distance(&v2), 7.0);
+        assert_eq!(v1.distance(&v2), 5.0);
+        assert_eq!(v2.manhattan_distance(&v1), 7.0);
+        assert_eq!(v1.manhattan_distance(&v1), 0.0);
+    }
+
+    #[test]
+    fn test_distance_to_segment() {
+        let a = Vector2D::from_xy(0.0, 0.0);
+        let b = Vector2D::from_xy(4.0, 0.0);
+
+        // projects inside the segment, so the distance is perpendicular
+        assert_eq!(Vector2D::from_xy(1.0, 3.0).distance_to_segment(&a, &b), 3.0);
+        assert_eq!(Vector2D::from_xy(2.0, 0.0).distance_to_segment(&a, &b), 0.0);
+
+        // projects past an endpoint, so the distance is to that endpoint
+        assert_eq!(Vector2D::from_xy(7.0, 4.0).distance_to_segment(&a, &b), 5.0);
+        assert_eq!(
+            Vector2D::from_xy(-3.0, -4.0).distance_to_segment(&a, &b),
+            5.0
+        );
+
+        // a degenerate segment is a point
+        assert_eq!(Vector2D::from_xy(3.0, 4.0).distance_to_segment(&a, &a), 5.0);
+    }
+
+    #[test]
+    fn test_cross() {
+        let v1 = Vector2D::from_xy(1.0, 0.0);
+        let v2 = Vector2D::from_xy(0.0, 2.0);
+        assert_eq!(v1.cross(&v2), 2.0);
+        assert_eq!(v2.cross(&v1), -2.0);
+        assert_eq!(v1.cross(&v1.scale(3.0)), 0.0);
+    }
+
+    #[test]
+    fn test_closest_points_on_segments() {
+        let a1 = Vector2D::from_xy(0.0, 0.0);
+        let a2 = Vector2D::from_xy(4.0, 0.0);
+
+        // a parallel segment above the middle of the first one
+        let b1 = Vector2D::from_xy(1.0, 2.0);
+        let b2 = Vector2D::from_xy(3.0, 2.0);
+        assert_eq!(a1.project_onto_segment(&b1, &b2), 0.0);
+        assert_eq!(
+            Vector2D::from_xy(2.0, 5.0).project_onto_segment(&b1, &b2),
+            0.5
+        );
+        let (s, t) = Vector2D::closest_points_on_segments(&a1, &a2, &b1, &b2);
+        assert_eq!(a1.lerp(&a2, s).distance(&b1.lerp(&b2, t)), 2.0);
+
+        // a segment pointing at the first one from beyond its end
+        let c1 = Vector2D::from_xy(6.0, 1.0);
+        let c2 = Vector2D::from_xy(5.0, 0.0);
+        assert_eq!(
+            Vector2D::closest_points_on_segments(&a1, &a2, &c1, &c2),
+            (1.0, 1.0)
+        );
+
+        // crossing segments meet at the crossing
+        let d1 = Vector2D::from_xy(1.0, -1.0);
+        let d2 = Vector2D::from_xy(1.0, 3.0);
+        assert_eq!(
+            Vector2D::closest_points_on_segments(&a1, &a2, &d1, &d2),
+            (0.25, 0.25)
+        );
+    }
+
+    #[test]
+    fn test_segments_cross() {
+        let a1 = Vector2D::from_xy(0.0, 0.0);
+        let a2 = Vector2D::from_xy(2.0, 2.0);
+
+        // an X crosses
+        let b1 = Vector2D::from_xy(0.0, 2.0);
+        let b2 = Vector2D::from_xy(2.0, 0.0);
+        assert!(Vector2D::segments_cross(&a1, &a2, &b1, &b2));
+
+        // segments that stop short, or only touch, do not
+        let short = Vector2D::from_xy(0.5, 1.0);
+        assert!(!Vector2D::segments_cross(&a1, &a2, &b1, &short));
+        let touching = Vector2D::from_xy(1.0, 1.0);
+        assert!(!Vector2D::segments_cross(&a1, &a2, &b1, &touching));
+        assert!(!Vector2D::segments_cross(&a1, &a2, &a2, &b2));
+
+        // neither do collinear, overlapping segments
+        let along = Vector2D::from_xy(3.0, 3.0);
+        assert!(!Vector2D::segments_cross(&a1, &a2, &touching, &along));
+    }
+
+    #[test]
+    fn test_abs() {
+        assert_eq!(
+            Vector2D::from_xy(-1.5, 2.0).abs(),
+            Vector2D::from_xy(1.5, 2.0)
+        );
+        assert_eq!(
+            Vector2D::from_xy(3.0, -0.0).abs(),
+            Vector2D::from_xy(3.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_hadamard() {
+        let v = Vector2D::from_xy(3.0, -2.0);
+        let zoom = Vector2D::from_xy(2.0, 0.5);
+        assert_eq!(v.hadamard(&zoom), Vector2D::from_xy(6.0, -1.0));
+        assert_eq!(v.hadamard(&zoom).hadamard_div(&zoom), v);
+        assert_eq!(
+            v.hadamard_div(&Vector2D::from_xy(4.0, -8.0)),
+            Vector2D::from_xy(0.75, 0.25)
+        );
+
+        // a zero component of the divisor zeroes that component only
+        let halved = v.hadamard_div(&Vector2D::from_xy(0.0, 2.0));
+        assert!(halved.is_finite());
+        assert_eq!(halved, Vector2D::from_xy(0.0, -1.0));
+    }
+
+    #[test]
+    fn test_signum() {
+        assert_eq!(
+            Vector2D::from_xy(-1.5, 2.0).signum(),
+            Vector2D::from_xy(-1.0, 1.0)
+        );
+        assert_eq!(
+            Vector2D::from_xy(0.0, -3.0).signum(),
+            Vector2D::from_xy(0.0, -1.0)
+        );
+        let zero = Vector2D::from_xy(-0.0, 0.0).signum();
+        assert_eq!(zero.x.to_bits(), 0.0_f64.to_bits());
+        assert_eq!(zero.y.to_bits(), 0.0_f64.to_bits());
+    }
+
+    #[test]
+    fn test_index() {
+        let mut v1 = Vector2D::from_xy(1.0, 2.0);
+        assert_eq!(v1[0], 1.0);
+        assert_eq!(v1[1], 2.0);
+
+        v1[0] = 3.0;
+        v1[1] += 2.0;
+        assert_eq!(v1, Vector2D::from_xy(3.0, 4.0));
+    }
+
+    #[test]
+    #[should_panic(expected = "out of range")]
+    fn test_index_out_of_range() {
+        let v1 = Vector2D::from_xy(1.0, 2.0);
+        let _ = v1[2];
+    }
+
+    #[test]
+    fn test_approx_eq() {
+        let v1 = Vector2D::from_xy(1.0, 2.0);
+        let inside = Vector2D::from_xy(1.0 + 0.9e-6, 2.0 - 0.9e-6);
+        let outside = Vector2D::from_xy(1.0, 2.0 + 1.1e-6);
+
+        assert!(v1.approx_eq(&inside, 1e-6));
+        assert!(!v1.approx_eq(&outside, 1e-6));
+        assert_vec_approx!(v1, inside, 1e-6);
+    }
+
+    #[test]
+    #[should_panic(expected = "left ~= right")]
+    fn test_assert_vec_approx_fails() {
+        let v1 = Vector2D::from_xy(1.0, 2.0);
+        let outside = Vector2D::from_xy(1.0, 2.0 + 1.1e-6);
+        assert_vec_approx!(v1, outside, 1e-6);
+    }
 }
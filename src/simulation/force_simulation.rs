@@ -1,20 +1,289 @@
-use crate::graph::{edge::Edge, node::Node};
+use crate::error::{Error, FieldError, Result};
+use crate::graph::{
+    csr_graph::CsrGraph,
+    edge::{Edge, EdgeMerge},
+    node::Node,
+};
 use crate::math::vector_2d::Vector2D;
+use crate::simulation::sim_params::{
+    DistanceMetric, EdgeRepulsion, Integrator, SimParams, WeightSemantics,
+};
 
-use std::ops::{Add, Mul, Sub};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::cell::OnceCell;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::ops::{Add, Mul, RangeInclusive, Sub};
+use std::time::Instant;
 
-#[derive(Debug, Clone)]
+/// A spring pulling a single node toward a fixed target position.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Anchor {
+    pub node_idx: usize,
+    pub target: Vector2D<f64>,
+    pub strength: f64,
+}
+
+/// A circular region that pushes out any node inside it, e.g. to keep the layout clear of a
+/// legend box.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Obstacle {
+    pub center: Vector2D<f64>,
+    pub radius: f64,
+    pub strength: f64,
+}
+
+/// Diagnostics describing a single call to [ForceSimulation::step](ForceSimulation::step).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepReport {
+    /// Largest distance any single node moved
+    pub max_displacement: f64,
+
+    /// Sum of the distances every node moved
+    pub total_movement: f64,
+
+    /// Kinetic energy of the nodes after the step
+    pub kinetic_energy: f64,
+
+    /// Cap on the distance a node could move during the step, if any
+    pub temperature: Option<f64>,
+}
+
+/// Snapshot of a running simulation with everything a front end needs to drive and draw the
+/// loop, from [ForceSimulation::state](ForceSimulation::state). It serializes far smaller than
+/// the full simulation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SimulationState {
+    /// Number of steps taken so far
+    pub step_count: usize,
+
+    /// Whether the last step moved every node less than the tolerance
+    pub settled: bool,
+
+    /// Kinetic energy of the nodes
+    pub kinetic_energy: f64,
+
+    /// Position of every node as `(id, x, y)`, in index order
+    pub positions: Vec<(usize, f64, f64)>,
+}
+
+/// Measures of the quality of a drawing, from
+/// [ForceSimulation::layout_quality](ForceSimulation::layout_quality). For every field, lower is
+/// better except `min_node_distance`, where higher is better.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutQuality {
+    /// Number of pairs of edges that cross
+    pub edge_crossings: usize,
+
+    /// Number of pairs of nodes whose circles overlap
+    pub node_overlaps: usize,
+
+    /// Standard deviation of the edge lengths divided by their mean, or 0 if there are no edges
+    pub edge_length_cv: f64,
+
+    /// Smallest distance between two nodes, or infinity if there are fewer than two nodes
+    pub min_node_distance: f64,
+}
+
+/// Defaults for the settings a saved state may be missing, e.g. one written before the setting
+/// was added. They match those of a simulation created with [SimParams::default].
+mod field_defaults {
+    use crate::math::vector_2d::Vector2D;
+    use crate::simulation::sim_params::SimParams;
+
+    pub fn time_step() -> f64 {
+        SimParams::default().time_step
+    }
+
+    pub fn repulsion_constant() -> f64 {
+        SimParams::default().repulsion_constant
+    }
+
+    pub fn attraction_constant() -> f64 {
+        SimParams::default().attraction_constant
+    }
+
+    pub fn attraction_exponent() -> f64 {
+        SimParams::default().attraction_exponent
+    }
+
+    pub fn repulsion_exponent() -> f64 {
+        SimParams::default().repulsion_exponent
+    }
+
+    pub fn origin() -> Vector2D<f64> {
+        Vector2D::from_xy(0.0, 0.0)
+    }
+
+    pub fn group_strength() -> f64 {
+        1.0
+    }
+
+    pub fn min_distance() -> f64 {
+        super::DEFAULT_MIN_DISTANCE
+    }
+}
+
+/// Smallest distance the pairwise forces are evaluated at, unless changed with
+/// [ForceSimulation::set_min_distance](ForceSimulation::set_min_distance).
+const DEFAULT_MIN_DISTANCE: f64 = 1e-5;
+
+/// 64-bit FNV-1a hash of everything written to it. Unlike the standard library's hashers, its
+/// output is specified, so it is the same on every run and every platform.
+struct Fnv1aWriter(u64);
+
+impl Fnv1aWriter {
+    fn new() -> Self {
+        Fnv1aWriter(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl std::io::Write for Fnv1aWriter {
+    fn write(&mut self, bytes: &[u8]) -> std::io::Result<usize> {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Buffers reused by every step, so a simulation animating at a high frame rate does not
+/// reallocate them each frame. They are cleared and refilled rather than replaced, which keeps
+/// their capacity, and hold nothing meaningful between steps.
+#[derive(Debug, Clone, Default)]
+struct ScratchBuffers {
+    /// Pairwise forces; only the first `n_nodes` entries of the first `n_nodes` rows are used
+    pairwise: Vec<Vec<Vector2D<f64>>>,
+    forces: Vec<Vector2D<f64>>,
+    /// Positions at the start of the last call to `integrate`
+    previous_positions: Vec<Vector2D<f64>>,
+    /// Accelerations at the start, and net forces at the end, of a velocity-Verlet step
+    accelerations: Vec<Vector2D<f64>>,
+    verlet_forces: Vec<Vector2D<f64>>,
+    /// Positions `verlet_forces` were computed at; empty once anything else they depend on changes
+    verlet_positions: Vec<Vector2D<f64>>,
+    /// Whether each node moved fast enough to stay awake on the last step
+    moving: Vec<bool>,
+}
+
+/// Version of the JSON format written by [ForceSimulation::to_json](ForceSimulation::to_json).
+/// Version 1 is the untagged format written before the version field existed. Version 3 changed
+/// the default attraction exponent from -2 to 1; older states that leave it out keep -2.
+pub const SERIALIZATION_VERSION: u32 = 3;
+
+/// Attraction exponent of a state written before version 3 that leaves it out, the
+/// inverse-square default of the time.
+const LEGACY_ATTRACTION_EXPONENT: f64 = -2.0;
+
+/// Only the nodes and edges are required in the serialized form. Every setting missing from it
+/// takes its default, and the caches derived from the nodes and edges are not serialized at all,
+/// but rebuilt on load.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ForceSimulation {
+    #[serde(default = "ForceSimulation::untagged_version")]
+    version: u32,
     nodes: Vec<Node>,
     edges: Vec<Edge>,
+    #[serde(skip)]
     positions: Vec<Vector2D<f64>>,
+    #[serde(skip)]
     velocities: Vec<Vector2D<f64>>,
-    distances: Vec<Vec<f64>>,
-    directions: Vec<Vec<f64>>,
+    #[serde(skip)]
     masses: Vec<f64>,
+    #[serde(skip)]
+    edge_index: HashMap<(usize, usize), usize>,
+    #[serde(skip)]
+    degrees: Vec<usize>,
+    #[serde(skip)]
+    id_index: OnceCell<HashMap<usize, usize>>,
+    #[serde(skip)]
+    last_forces: Vec<Vector2D<f64>>,
+    #[serde(default = "field_defaults::time_step")]
     time_step: f64,
+    #[serde(default = "field_defaults::repulsion_constant")]
     repulsion_constant: f64,
+    #[serde(default = "field_defaults::attraction_constant")]
     attraction_constant: f64,
+    #[serde(default = "field_defaults::attraction_exponent")]
+    attraction_exponent: f64,
+    #[serde(default = "field_defaults::repulsion_exponent")]
+    repulsion_exponent: f64,
+    #[serde(default)]
+    repulsion_cutoff: Option<f64>,
+    #[serde(default)]
+    degree_scaled_repulsion: bool,
+    #[serde(default)]
+    damping: f64,
+    #[serde(default)]
+    gravity: f64,
+    #[serde(default = "field_defaults::origin")]
+    gravity_center: Vector2D<f64>,
+    #[serde(default)]
+    temperature: Option<f64>,
+    #[serde(default)]
+    bounds: Option<(Vector2D<f64>, Vector2D<f64>)>,
+    #[serde(default)]
+    lock_centroid: bool,
+    #[serde(default)]
+    remove_angular_momentum: bool,
+    #[serde(default)]
+    anchors: Vec<Anchor>,
+    #[serde(default)]
+    obstacles: Vec<Obstacle>,
+    #[serde(default = "field_defaults::group_strength")]
+    intra_group_strength: f64,
+    #[serde(default = "field_defaults::group_strength")]
+    inter_group_strength: f64,
+    #[serde(default)]
+    seed: u64,
+    #[serde(default)]
+    jitter_count: u64,
+    #[serde(default)]
+    integrator: Integrator,
+    #[serde(default)]
+    weight_semantics: WeightSemantics,
+    #[serde(default)]
+    distance_metric: DistanceMetric,
+    #[serde(default)]
+    edge_repulsion: Option<EdgeRepulsion>,
+    #[serde(default)]
+    deterministic: bool,
+    #[serde(default)]
+    warmup_steps: usize,
+    #[serde(default)]
+    steps_taken: usize,
+    #[serde(default = "field_defaults::min_distance")]
+    min_distance: f64,
+    #[serde(default)]
+    auto_time_step: bool,
+    #[serde(default)]
+    clean_edges: bool,
+    #[serde(default)]
+    sleep_threshold: Option<(f64, usize)>,
+    #[serde(default)]
+    still_steps: Vec<usize>,
+    #[serde(default)]
+    asleep: Vec<bool>,
+    #[serde(skip)]
+    last_sent_positions: Vec<Vector2D<f64>>,
+    #[serde(skip)]
+    last_report: Option<StepReport>,
+    #[serde(skip)]
+    scratch: ScratchBuffers,
+}
+
+impl Default for ForceSimulation {
+    fn default() -> ForceSimulation {
+        ForceSimulation::new_with_params(vec![], vec![], SimParams::default())
+    }
 }
 
 impl ForceSimulation {
@@ -25,347 +294,2480 @@ impl ForceSimulation {
         repulsion_constant: f64,
         attraction_constant: f64,
     ) -> Self {
+        let params = SimParams {
+            time_step,
+            repulsion_constant,
+            attraction_constant,
+            ..SimParams::default()
+        };
+        ForceSimulation::new_with_params(nodes, edges, params)
+    }
+
+    /// Creates a new simulation from named parameters. Prefer this over
+    /// [ForceSimulation::new](ForceSimulation::new), whose three bare `f64` arguments are easy to
+    /// transpose.
+    pub fn new_with_params(nodes: Vec<Node>, edges: Vec<Edge>, params: SimParams) -> Self {
         let n_nodes: usize = nodes.len();
         let n_edges: usize = edges.len();
 
         let positions: Vec<Vector2D<f64>> = nodes.iter().map(|node| node.position).collect();
         let velocities: Vec<Vector2D<f64>> = nodes.iter().map(|node| node.velocity).collect();
 
-        // Initialize distances and directions matrices with default values
-        let n_nodes = nodes.len();
-        let distances = vec![vec![0.0; n_nodes]; n_nodes];
-        let directions = vec![vec![0.0; n_nodes]; n_nodes];
-
         let masses: Vec<f64> = nodes.iter().map(|node| node.mass).collect();
 
-        ForceSimulation {
+        let mut simulation = ForceSimulation {
+            version: SERIALIZATION_VERSION,
             nodes,
             edges,
-            time_step,
-            repulsion_constant,
-            attraction_constant,
+            time_step: params.time_step,
+            repulsion_constant: params.repulsion_constant,
+            attraction_constant: params.attraction_constant,
+            attraction_exponent: params.attraction_exponent,
+            repulsion_exponent: params.repulsion_exponent,
+            repulsion_cutoff: params.repulsion_cutoff,
+            degree_scaled_repulsion: params.degree_scaled_repulsion,
+            damping: params.damping,
+            gravity: params.gravity,
+            gravity_center: Vector2D::from_xy(0.0, 0.0),
+            temperature: params.temperature,
+            bounds: None,
+            lock_centroid: false,
+            remove_angular_momentum: false,
             positions,
             velocities,
-            distances,
-            directions,
             masses,
+            edge_index: HashMap::new(),
+            degrees: vec![],
+            id_index: OnceCell::new(),
+            last_forces: vec![Vector2D::from_xy(0.0, 0.0); n_nodes],
+            anchors: vec![],
+            obstacles: vec![],
+            intra_group_strength: 1.0,
+            inter_group_strength: 1.0,
+            seed: params.seed,
+            jitter_count: 0,
+            integrator: params.integrator,
+            weight_semantics: params.weight_semantics,
+            distance_metric: params.distance_metric,
+            edge_repulsion: params.edge_repulsion,
+            deterministic: params.deterministic,
+            warmup_steps: 0,
+            steps_taken: 0,
+            min_distance: DEFAULT_MIN_DISTANCE,
+            auto_time_step: params.auto_time_step,
+            clean_edges: params.clean_edges,
+            sleep_threshold: None,
+            still_steps: vec![],
+            asleep: vec![],
+            scratch: ScratchBuffers::default(),
+            last_sent_positions: vec![],
+            last_report: None,
+        };
+
+        simulation.derive_from_edges();
+        if let Some(jitter) = params.coincident_jitter {
+            simulation.ensure_distinct_positions(jitter, params.seed);
         }
+        simulation
     }
 
-    fn acceleration_from_force_n1_exerts_on_n2(
-        &self,
-        n1: &Node,
-        n2: &Node,
-        weight: f64,
-    ) -> Vector2D<f64> {
-        let total_force = self.total_force_n1_exerts_on_n2(n1, n2, weight);
-        total_force / n2.mass
+    /// Cleans the edges if the simulation was created to, then builds the edge index and sets
+    /// each node's mass to the sum of the absolute weights of its incident edges.
+    fn derive_from_edges(&mut self) {
+        if self.clean_edges {
+            self.clean_edges();
+        }
+        self.update_edge_index();
+        self.update_masses();
     }
 
-    fn attractive_force_n1_exerts_on_n2(
-        &self,
-        distance: f64,
-        weight: f64,
-        direction: f64,
-    ) -> Vector2D<f64> {
-        let magnitude = self.attraction_constant * weight / distance.powi(2);
-        Vector2D::from_rtheta(magnitude, direction)
+    /// Same as [ForceSimulation::new_with_params](ForceSimulation::new_with_params), with every node
+    /// placed uniformly at random in the square from `(-extent, -extent)` to `(extent, extent)`.
+    /// The positions are drawn from `rng`, so a seeded generator gives a reproducible initial
+    /// layout.
+    pub fn new_with_random_positions<R: Rng>(
+        mut nodes: Vec<Node>,
+        edges: Vec<Edge>,
+        params: SimParams,
+        extent: f64,
+        rng: &mut R,
+    ) -> Self {
+        let extent = extent.abs();
+        for node in nodes.iter_mut() {
+            node.position = Vector2D::from_xy(
+                rng.gen_range(-extent..=extent),
+                rng.gen_range(-extent..=extent),
+            );
+        }
+        ForceSimulation::new_with_params(nodes, edges, params)
     }
 
-    ///
-    fn chg_in_position_from_force_n1_exerts_on_n2(
-        &self,
-        n1: &Node,
-        n2: &Node,
-        weight: f64,
-        delta_time: f64,
-    ) -> Vector2D<f64> {
-        // inputs for basic kinematics equation
-        let v0 = n2.velocity;
-        let p0 = n2.position;
-        let a = self.acceleration_from_force_n1_exerts_on_n2(n1, n2, weight);
+    /// Fallible version of [ForceSimulation::new_with_params](ForceSimulation::new_with_params),
+    /// returning [Error::DanglingNodeIndex] if an edge refers to a node that does not exist, or
+    /// [Error::InvalidCanvasSize] for a toroidal metric without a proper canvas, instead of
+    /// panicking or producing NaN later.
+    pub fn try_new_with_params(
+        nodes: Vec<Node>,
+        edges: Vec<Edge>,
+        params: SimParams,
+    ) -> Result<Self> {
+        params.distance_metric.validate()?;
+        for edge in edges.iter() {
+            for idx in [edge.node1_idx, edge.node2_idx] {
+                if idx >= nodes.len() {
+                    return Err(Error::DanglingNodeIndex(idx));
+                }
+            }
+        }
+        Ok(ForceSimulation::new_with_params(nodes, edges, params))
+    }
 
-        // basic kinematics equation
-        let pf = p0 + (v0 * delta_time) + (a * delta_time.powi(2) / 2.0);
+    /// Restores a simulation from the JSON produced by
+    /// [ForceSimulation::to_json](ForceSimulation::to_json). JSON written by an older version may
+    /// be missing fields added since, which take their default values. JSON written by a newer
+    /// version is rejected with [Error::UnsupportedVersion], since its fields cannot be trusted
+    /// to mean the same thing. As on creation, each node's mass is set from its incident edges,
+    /// and only an isolated node keeps the mass given in the JSON.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut value: serde_json::Value = serde_json::from_str(json)?;
+        let version = value
+            .get("version")
+            .and_then(|version| version.as_u64())
+            .map_or(ForceSimulation::untagged_version(), |version| {
+                version as u32
+            });
+        if version > SERIALIZATION_VERSION {
+            return Err(Error::UnsupportedVersion(version));
+        }
+        if version < 3 {
+            if let Some(object) = value.as_object_mut() {
+                object
+                    .entry("attraction_exponent")
+                    .or_insert(serde_json::json!(LEGACY_ATTRACTION_EXPONENT));
+            }
+        }
 
-        // delta_p = pf - p0
-        pf - p0
+        let mut simulation: ForceSimulation = serde_json::from_value(value)?;
+        simulation.distance_metric.validate()?;
+        simulation.version = SERIALIZATION_VERSION;
+        simulation.rebuild_caches();
+        Ok(simulation)
     }
 
-    fn chg_in_velocity_from_force_n1_exerts_on_n2(
-        &self,
-        n1: &Node,
-        n2: &Node,
-        weight: f64,
-        delta_time: f64,
-    ) -> Vector2D<f64> {
-        let acceleration = self.acceleration_from_force_n1_exerts_on_n2(n1, n2, weight);
-        n2.velocity + acceleration * delta_time
+    fn untagged_version() -> u32 {
+        1
     }
 
-    fn get_edge_connecting_nodes(&self, node1_idx: usize, node2_idx: usize) -> Option<&Edge> {
-        self.edges
-            .iter()
-            .find(|&edge| edge.has_node(node1_idx) && edge.has_node(node2_idx))
+    /// Same as [ForceSimulation::from_json](ForceSimulation::from_json), but for hand-written JSON:
+    /// before deserializing, it checks every node and edge and returns [Error::InvalidFields]
+    /// listing each field that is missing, of the wrong type or out of range, such as an edge
+    /// referring to a node that does not exist, rather than stopping at the first serde error.
+    /// Negative weights are only rejected under [WeightSemantics::Distance], where they would be
+    /// negative target distances.
+    pub fn from_json_validated(json: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        let mut errors = vec![];
+
+        let nodes = ForceSimulation::validated_array(&value, "nodes", &mut errors);
+        for (i, node) in nodes.iter().enumerate() {
+            let location = format!("nodes[{}]", i);
+            if !node.is_object() {
+                errors.push(FieldError::new(&location, "", "expected an object"));
+                continue;
+            }
+            ForceSimulation::validate_index(node, &location, "id", None, &mut errors);
+            match node.get("position") {
+                None => errors.push(FieldError::new(&location, "position", "missing")),
+                Some(position) => {
+                    for axis in ["x", "y"] {
+                        if !position.get(axis).is_some_and(|v| v.is_number()) {
+                            let field = format!("position.{}", axis);
+                            errors.push(FieldError::new(&location, &field, "expected a number"));
+                        }
+                    }
+                }
+            }
+            for field in ["mass", "radius"] {
+                match node.get(field) {
+                    Some(v) if !v.as_f64().is_some_and(|v| v > 0.0) => errors.push(
+                        FieldError::new(&location, field, "expected a positive number"),
+                    ),
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some(Err(Error::InvalidCanvasSize(width, height))) = value
+            .get("distance_metric")
+            .and_then(|metric| serde_json::from_value::<DistanceMetric>(metric.clone()).ok())
+            .map(|metric| metric.validate())
+        {
+            let problem = format!(
+                "canvas size {} x {} is not positive and finite",
+                width, height
+            );
+            errors.push(FieldError::new("", "distance_metric", &problem));
+        }
+
+        let distance_weights = value
+            .get("weight_semantics")
+            .is_some_and(|semantics| semantics == "Distance");
+        let edges = ForceSimulation::validated_array(&value, "edges", &mut errors);
+        for (i, edge) in edges.iter().enumerate() {
+            let location = format!("edges[{}]", i);
+            if !edge.is_object() {
+                errors.push(FieldError::new(&location, "", "expected an object"));
+                continue;
+            }
+            for field in ["node1_idx", "node2_idx"] {
+                ForceSimulation::validate_index(
+                    edge,
+                    &location,
+                    field,
+                    Some(nodes.len()),
+                    &mut errors,
+                );
+            }
+            match edge.get("weight").map(|weight| weight.as_f64()) {
+                None => errors.push(FieldError::new(&location, "weight", "missing")),
+                Some(None) => {
+                    errors.push(FieldError::new(&location, "weight", "expected a number"))
+                }
+                Some(Some(weight)) if distance_weights && weight < 0.0 => {
+                    let problem = format!("negative target distance {}", weight);
+                    errors.push(FieldError::new(&location, "weight", &problem));
+                }
+                Some(Some(_)) => {}
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(Error::InvalidFields(errors));
+        }
+        ForceSimulation::from_json(json)
     }
 
-    pub fn get_edges(&self) -> &Vec<Edge> {
-        &self.edges
+    /// Returns the array under `key`, recording an error if it is missing or something other than
+    /// an array.
+    fn validated_array<'a>(
+        value: &'a serde_json::Value,
+        key: &str,
+        errors: &mut Vec<FieldError>,
+    ) -> &'a [serde_json::Value] {
+        match value.get(key) {
+            None => {
+                errors.push(FieldError::new("", key, "missing"));
+                &[]
+            }
+            Some(serde_json::Value::Array(items)) => items,
+            Some(_) => {
+                errors.push(FieldError::new("", key, "expected an array"));
+                &[]
+            }
+        }
     }
 
-    /// Returns a vector of edges that are connected to the node with the provided index.
-    fn get_edges_by_node_idx(&self, node_idx: usize) -> Vec<&Edge> {
-        let mut edges: Vec<&Edge> = Vec::new();
-        for edge in &self.edges {
-            if (edge.has_node(node_idx)) {
-                edges.push(edge);
+    /// Records an error unless `item[field]` is a non-negative integer, below `len` if given.
+    fn validate_index(
+        item: &serde_json::Value,
+        location: &str,
+        field: &str,
+        len: Option<usize>,
+        errors: &mut Vec<FieldError>,
+    ) {
+        match item.get(field).map(|idx| idx.as_u64()) {
+            None => errors.push(FieldError::new(location, field, "missing")),
+            Some(None) => errors.push(FieldError::new(
+                location,
+                field,
+                "expected a non-negative integer",
+            )),
+            Some(Some(idx)) => {
+                if let Some(len) = len.filter(|&len| idx as usize >= len) {
+                    let problem = format!("index {} is out of range ({} nodes)", idx, len);
+                    errors.push(FieldError::new(location, field, &problem));
+                }
             }
         }
-        edges
     }
 
-    fn get_n_nodes(&self) -> usize {
-        self.nodes.len()
+    /// Rebuilds every cache derived from the nodes and edges, for a simulation whose caches are
+    /// missing, e.g. one deserialized from JSON. The masses are derived from the edges just as
+    /// [ForceSimulation::new_with_params](ForceSimulation::new_with_params) derives them, so the
+    /// same graph simulates the same way however it was built.
+    fn rebuild_caches(&mut self) {
+        let n_nodes = self.n_nodes();
+        self.positions = self.nodes.iter().map(|node| node.position).collect();
+        self.velocities = self.nodes.iter().map(|node| node.velocity).collect();
+        self.masses = self.nodes.iter().map(|node| node.mass).collect();
+        self.last_forces
+            .resize(n_nodes, Vector2D::from_xy(0.0, 0.0));
+        self.derive_from_edges();
     }
 
-    fn get_node_mass(&self, node_idx: usize) -> f64 {
-        let mut total_mass: f64 = 0.0;
-        for edge in &self.edges {
-            if edge.node1_idx == node_idx || edge.node2_idx == node_idx {
-                total_mass += edge.weight;
-            }
+    /// Returns a hash of the full simulation state: the nodes, the edges, every parameter, the
+    /// seed and the number of steps taken. The layout is a deterministic function of these, so
+    /// callers can use the hash as a key to memoize converged layouts and skip recomputing them.
+    /// The hash is stable across runs, but covers cosmetic fields such as labels and colors too.
+    pub fn state_hash(&self) -> u64 {
+        // The JSON form holds no maps, so it never depends on HashMap iteration order, and
+        // writing it cannot fail
+        let mut hasher = Fnv1aWriter::new();
+        let _ = serde_json::to_writer(&mut hasher, self);
+        hasher.0
+    }
+
+    /// Returns a short, stable fingerprint of the current layout: a 16-digit hex hash of every
+    /// node's id and position, rounded to 6 decimal places and ordered by id. Pasting the
+    /// fingerprint of a converged layout into a test catches any change that alters the numerical
+    /// output, while the rounding absorbs floating-point noise far below it. Only positions
+    /// count, so relabeling a node or reordering the nodes leaves the fingerprint unchanged.
+    pub fn layout_fingerprint(&self) -> String {
+        let mut positions: Vec<(usize, Vector2D<f64>)> = self
+            .nodes
+            .iter()
+            .map(|node| (node.id, node.position.round(6)))
+            .collect();
+        positions.sort_by_key(|&(id, _)| id);
+
+        let mut hasher = Fnv1aWriter::new();
+        for (id, position) in positions {
+            // Adding 0 turns -0 into 0, so the two hash alike
+            let _ = write!(hasher, "{}:{},{};", id, position.x + 0.0, position.y + 0.0);
         }
-        total_mass
+        format!("{:016x}", hasher.0)
     }
 
-    pub fn get_nodes(&self) -> &Vec<Node> {
-        &self.nodes
+    /// Serializes the full simulation state, including its parameters and seed, to JSON.
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
     }
 
-    fn repulsive_force_n1_exerts_on_n2(
-        &self,
-        distance: f64,
-        n1_mass: f64,
-        n2_mass: f64,
-        direction: f64,
-    ) -> Vector2D<f64> {
-        let magnitude = self.repulsion_constant * n1_mass * n2_mass / distance.powi(2);
-        Vector2D::from_rtheta(magnitude, direction)
+    /// Perturbs every movable node position by a random offset of at most `magnitude` in each
+    /// component. Fixed nodes stay where they are. This is useful to break the symmetry of a
+    /// layout that is stuck in a degenerate configuration. The offsets are drawn from `seed`, so
+    /// the same seed always produces the same perturbation. A `magnitude` that is not finite
+    /// moves nothing.
+    pub fn add_jitter(&mut self, magnitude: f64, seed: u64) {
+        self.add_jitter_with_rng(magnitude, &mut StdRng::seed_from_u64(seed));
     }
 
-    fn total_force_n1_exerts_on_n2(&self, n1: &Node, n2: &Node, weight: f64) -> Vector2D<f64> {
-        let distance = n1.position.distance(&n2.position).max(1e-5); // Avoid division by zero
-        let direction = n1.position.relative_to(&n2.position).angle();
-        let repulsive_force =
-            self.repulsive_force_n1_exerts_on_n2(distance, n1.mass, n2.mass, direction);
-        let attractive_force = self.attractive_force_n1_exerts_on_n2(distance, weight, direction);
-        attractive_force - repulsive_force
+    /// Same as [ForceSimulation::add_jitter](ForceSimulation::add_jitter), drawing the offsets
+    /// from the simulation's own seed. Each call continues the sequence of the previous ones, so
+    /// two simulations with the same seed and history, including one restored from JSON, always
+    /// produce the same sequence of perturbations.
+    pub fn add_jitter_from_state(&mut self, magnitude: f64) {
+        // Each call draws from its own stream, keyed by the seed and the number of earlier calls
+        let mut key = [0u8; 32];
+        key[..8].copy_from_slice(&self.seed.to_le_bytes());
+        key[8..16].copy_from_slice(&self.jitter_count.to_le_bytes());
+        let mut rng = StdRng::from_seed(key);
+        self.jitter_count += 1;
+        self.add_jitter_with_rng(magnitude, &mut rng);
     }
 
-    /// Updates the distances cache based on the current positions of the nodes.
-    pub fn update_distances(&mut self) {
-        for i in 0..self.nodes.len() {
-            self.distances[i][i] = 0.0; // Distance to itself is always 0
-            for j in (i + 1)..self.nodes.len() {
-                let distance = self.nodes[i].position.distance(&self.nodes[j].position);
-                // Since the distance is symmetrical, assign it to both [i][j] and [j][i]
-                self.distances[i][j] = distance;
-                self.distances[j][i] = distance;
-            }
+    /// Same as [ForceSimulation::add_jitter](ForceSimulation::add_jitter), drawing the offsets
+    /// from `rng` instead of a seed, so callers can inject their own source of randomness.
+    pub fn add_jitter_with_rng<R: Rng>(&mut self, magnitude: f64, rng: &mut R) {
+        let magnitude = magnitude.abs();
+        if !magnitude.is_finite() {
+            return;
         }
+        for node in self.nodes.iter_mut().filter(|node| !node.fixed) {
+            let offset = Vector2D::from_xy(
+                rng.gen_range(-magnitude..=magnitude),
+                rng.gen_range(-magnitude..=magnitude),
+            );
+            node.position += offset;
+        }
+        self.update_positions();
     }
 
-    /// Updates the directions cache based on the current positions of the nodes.
-    pub fn update_directions(&mut self) {
-        for i in 0..self.nodes.len() {
-            self.directions[i][i] = 0.0; // Angle to itself is always 0
-            for j in (i + 1)..self.nodes.len() {
-                let angle = self.nodes[i]
-                    .position
-                    .relative_to(&self.nodes[j].position)
-                    .angle();
+    /// Nudges apart nodes that share a position, returning how many were moved. Nodes built
+    /// without a position all start at the same default point, where the forces between them
+    /// are enormous and point in an arbitrary direction. Fixed nodes and the first movable node at
+    /// each free position stay put, and every later one is offset by at most `jitter` in each
+    /// component, drawn from `seed`, until it lands on a free position. A `jitter` that is 0 or
+    /// not finite moves nothing.
+    pub fn ensure_distinct_positions(&mut self, jitter: f64, seed: u64) -> usize {
+        let jitter = jitter.abs();
+        if jitter == 0.0 || !jitter.is_finite() {
+            return 0;
+        }
 
-                // Since the angle is anti-symmetrical, calculate for j > i and infer for j < i
-                self.directions[i][j] = angle;
-                // Normalize the angle to be within the range [0, 2π]
-                self.directions[j][i] =
-                    (angle + std::f64::consts::PI) % (2.0 * std::f64::consts::PI);
+        // f64 is not Hash, but identical positions have identical bits
+        let key = |position: Vector2D<f64>| (position.x.to_bits(), position.y.to_bits());
+        let mut rng = StdRng::seed_from_u64(seed);
+        // Fixed nodes cannot make way, so they claim their positions first
+        let mut occupied: HashSet<(u64, u64)> = self
+            .nodes
+            .iter()
+            .filter(|node| node.fixed)
+            .map(|node| key(node.position))
+            .collect();
+        let mut moved = 0;
+        for node in self.nodes.iter_mut().filter(|node| !node.fixed) {
+            if !occupied.insert(key(node.position)) {
+                moved += 1;
+                while !occupied.insert(key(node.position)) {
+                    node.position += Vector2D::from_xy(
+                        rng.gen_range(-jitter..=jitter),
+                        rng.gen_range(-jitter..=jitter),
+                    );
+                }
             }
         }
+        self.update_positions();
+        moved
     }
 
-    /// Updates the positions cache based on the current positions of the nodes.
-    pub fn update_positions(&mut self) {
-        for i in 0..self.nodes.len() {
-            self.positions[i] = self.nodes[i].position;
-        }
+    /// Returns whether any two nodes share a position, which
+    /// [ForceSimulation::ensure_distinct_positions](ForceSimulation::ensure_distinct_positions)
+    /// would fix.
+    pub fn has_coincident_positions(&self) -> bool {
+        let mut seen = HashSet::new();
+        !self
+            .nodes
+            .iter()
+            .all(|node| seen.insert((node.position.x.to_bits(), node.position.y.to_bits())))
     }
 
-    /// Updates the velocities cache based on the current velocities of the nodes.
-    pub fn update_velocities(&mut self) {
-        for i in 0..self.nodes.len() {
-            self.velocities[i] = self.nodes[i].velocity;
+    /// Deterministically permutes the ids of the nodes among themselves, leaving the nodes, their
+    /// indices and the edges untouched. Tests built on layouts where every id equals its index
+    /// can use this to check that id-based lookups do not secretly assume it.
+    pub fn shuffle_ids(&mut self, seed: u64) {
+        let mut ids: Vec<usize> = self.nodes.iter().map(|node| node.id).collect();
+        ids.shuffle(&mut StdRng::seed_from_u64(seed));
+        for (node, id) in self.nodes.iter_mut().zip(ids) {
+            node.id = id;
         }
+        self.id_index.take();
     }
 
-    /// Updates the masses cache based on the current masses of the nodes.
-    pub fn update_masses(&mut self) {
-        for i in 0..self.nodes.len() {
-            self.masses[i] = self.get_node_mass(i);
-        }
+    /// Returns the seed used for every random perturbation of the layout.
+    pub fn seed(&self) -> u64 {
+        self.seed
     }
 
-    /// Performs a single simulation step.
-    pub fn step(&mut self) {
-        self.update_distances();
-        self.update_directions();
-        self.apply_forces();
-        self.update_positions_and_velocities();
+    /// Returns the corners `(min, max)` of the smallest axis-aligned rectangle containing every
+    /// node, or `None` if there are no nodes.
+    pub fn bounding_box(&self) -> Option<(Vector2D<f64>, Vector2D<f64>)> {
+        let first = self.nodes.first()?.position;
+        Some(self.nodes.iter().fold((first, first), |(min, max), node| {
+            (
+                Vector2D::from_xy(min.x.min(node.position.x), min.y.min(node.position.y)),
+                Vector2D::from_xy(max.x.max(node.position.x), max.y.max(node.position.y)),
+            )
+        }))
     }
 
-    /// Calculates all pairwise forces between nodes.
-    fn calculate_forces(&mut self) {
-        // Initialize a matrix of vectors to store the total forces that each node exerts on each
-        // other node. The matrix is anti-symmetrical, so the force that node i exerts on node j is
-        // the negative of the force that node j exerts on node i.
-        let total_forces: Vec<Vec<Vector2D<f64>>> =
-            vec![vec![Vector2D::from_xy(0.0, 0.0); self.get_n_nodes()]; self.get_n_nodes()];
+    /// Returns the indices of the nodes within `radius` of `center`, in index order, e.g. to
+    /// select the nodes near the cursor. A node exactly `radius` away is included. This scans
+    /// every node; it reads nothing but the positions, so a spatial index can take over without
+    /// changing callers.
+    pub fn nodes_within(&self, center: Vector2D<f64>, radius: f64) -> Vec<usize> {
+        (0..self.n_nodes())
+            .filter(|&node_idx| self.nodes[node_idx].position.distance(&center) <= radius)
+            .collect()
+    }
 
-        // Loop over all pairs i, j of nodes
-        for i in 0..self.get_n_nodes() {
-            for j in (i + 1)..self.get_n_nodes() {
-                let distance = self.distances[i][j];
-                let direction = self.directions[i][j];
-                let n1_mass = self.masses[i];
-                let n2_mass = self.masses[j];
-                let weight = self.get_edge_connecting_nodes(i, j).unwrap().weight;
+    /// Returns the point halfway between the endpoints of the edge at `edge_idx`, or `None` if
+    /// there is no such edge.
+    pub fn edge_midpoint(&self, edge_idx: usize) -> Option<Vector2D<f64>> {
+        let edge = self.edges.get(edge_idx)?;
+        let start = self.nodes[edge.node1_idx].position;
+        let end = self.nodes[edge.node2_idx].position;
+        Some(start.lerp(&end, 0.5))
+    }
 
-                // Calculate the total force that node i exerts on node j
-                total_forces[i][j] =
-                    self.total_force_n1_exerts_on_n2(&self.nodes[i], &self.nodes[j], weight);
+    /// Returns where to draw the label of the edge at `edge_idx`: its midpoint, pushed `offset`
+    /// along the edge's unit normal so the text does not sit on the line. The normal is the
+    /// direction from the edge's first node to its second, turned a quarter counterclockwise, so
+    /// a negative offset puts the label on the other side. Returns `None` if there is no such
+    /// edge.
+    pub fn edge_label_anchor(&self, edge_idx: usize, offset: f64) -> Option<Vector2D<f64>> {
+        let midpoint = self.edge_midpoint(edge_idx)?;
+        let edge = &self.edges[edge_idx];
+        let direction = self.nodes[edge.node2_idx].position - self.nodes[edge.node1_idx].position;
+        Some(midpoint + direction.orthonormal() * offset)
+    }
 
-                // Force is anti-symmetrical, so the force that node j exerts on node i is the
-                // negative of the force that node i exerts on node j
-                total_forces[j][i] = -total_forces[i][j];
-            }
+    /// Styles the drawing from the structure of the graph, as a readable default: each node's
+    /// radius grows linearly with its degree, from the start of `radius` for the lowest degree to
+    /// its end for the highest, and each edge's width grows linearly with the magnitude of its
+    /// weight across `width` in the same way. If every node has the same degree, or every edge
+    /// the same weight, they all get the middle of the range.
+    pub fn auto_style(&mut self, radius: RangeInclusive<f64>, width: RangeInclusive<f64>) {
+        let degrees: Vec<f64> = self.degrees.iter().map(|&degree| degree as f64).collect();
+        for (node, r) in self
+            .nodes
+            .iter_mut()
+            .zip(ForceSimulation::rescale(&degrees, &radius))
+        {
+            node.radius = r;
         }
 
-        // Return the total forces
-        total_forces
+        let weights: Vec<f64> = self.edges.iter().map(|edge| edge.weight.abs()).collect();
+        for (edge, w) in self
+            .edges
+            .iter_mut()
+            .zip(ForceSimulation::rescale(&weights, &width))
+        {
+            edge.width = w;
+        }
     }
 
-    /// Applies forces between all pairs of nodes to get the change in position and velocity. The
-    /// change in position and velocity is returned as a tuple of two vectors of 2D vectors. The
-    /// first vector contains the change in position vectors for each node, and the second vector
-    /// contains the change in velocity vectors for each node.
-    ///
-    /// Note that we have made a simplifying assumption that the change in position and velocity
-    /// vectors for each node is independent of the change in position and velocity vectors for
-    /// other nodes. This is not true in general, but it is a reasonable approximation for small
-    /// time steps.
-    ///
-    fn apply_forces(&mut self) -> (Vec<Vec<Vector2D<f64>>>, Vec<Vec<Vector2D<f64>>>) {
-        let force: Vec<Vec<Vector2D<f64>>> = self.calculate_forces();
-        let delta_time = self.time_step;
-
-        // Allocate memory for the total change in position and velocity vectors & init to 0
-        let delta_p: vec![vec![Vector2D::from_xy(0.0, 0.0); self.get_n_nodes()]];
-        let delta_v: vec![vec![Vector2D::from_xy(0.0, 0.0); self.get_n_nodes()]];
-
-        // Loop over all pairs i, j of nodes, adding the change in position and velocity vectors
-        // for each pair to get the total change in position and velocity vectors
-        for i in 0..(self.get_n_nodes() - 1) {
-            for j in 0..(self.get_n_nodes() - 1) {
-                let weight = self.get_edge_connecting_nodes(i, j).unwrap().weight;
-
-                // Calculate the change in position of node j due to the force that node i exerts
-                // on node j
-                delta_p[i] += self.chg_in_position_from_force_n1_exerts_on_n2(
-                    &self.nodes[i],
-                    &self.nodes[j],
-                    weight,
-                    delta_time,
-                );
+    /// Maps `values` linearly onto `range`, the smallest to its start and the largest to its end,
+    /// or every value to its middle if they are all equal.
+    fn rescale(values: &[f64], range: &RangeInclusive<f64>) -> Vec<f64> {
+        let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let (start, end) = (*range.start(), *range.end());
+        values
+            .iter()
+            .map(|&value| {
+                let t = if max > min {
+                    (value - min) / (max - min)
+                } else {
+                    0.5
+                };
+                start + t * (end - start)
+            })
+            .collect()
+    }
 
-                // Calculate the change in velocity of node j due to the force that node i exerts
-                // on node j
-                delta_v[i] += self.chg_in_velocity_from_force_n1_exerts_on_n2(
-                    &self.nodes[i],
-                    &self.nodes[j],
-                    weight,
-                    delta_time,
-                );
+    /// Counts the pairs of edges whose segments cross at the current node positions, a standard
+    /// measure of the quality of a drawing where lower is better. Edges sharing a node never
+    /// count as crossing, and neither do segments that only touch.
+    ///
+    /// ### See also
+    /// [Vector2D::segments_cross](Vector2D::segments_cross)
+    pub fn edge_crossings(&self) -> usize {
+        let endpoints = |edge: &Edge| {
+            (
+                self.nodes[edge.node1_idx].position,
+                self.nodes[edge.node2_idx].position,
+            )
+        };
+        let mut crossings = 0;
+        for (i, e1) in self.edges.iter().enumerate() {
+            for e2 in self.edges.iter().skip(i + 1) {
+                if e1.has_node(e2.node1_idx) || e1.has_node(e2.node2_idx) {
+                    continue;
+                }
+                let (a1, a2) = endpoints(e1);
+                let (b1, b2) = endpoints(e2);
+                if Vector2D::segments_cross(&a1, &a2, &b1, &b2) {
+                    crossings += 1;
+                }
             }
         }
+        crossings
+    }
 
-        // Return the total change in position and velocity vectors
-        (delta_p, delta_v)
+    /// Counts the pairs of nodes whose circles, of the nodes' radii, overlap. Circles that only
+    /// touch do not overlap.
+    ///
+    /// ### See also
+    /// [ForceSimulation::total_overlap_area](ForceSimulation::total_overlap_area)
+    pub fn overlap_count(&self) -> usize {
+        self.node_pairs()
+            .filter(|&(i, j)| self.overlap_area(i, j) > 0.0)
+            .count()
     }
 
-    /// Updates positions and velocities of all nodes based on the forces.
-    fn update_positions_and_velocities(&mut self) {
-        let (delta_p, delta_v) = self.apply_forces();
+    /// Returns the sum, over every pair of nodes, of the area where their circles overlap. This
+    /// measures how far the layout is from being free of overlaps, not just how many remain.
+    pub fn total_overlap_area(&self) -> f64 {
+        self.node_pairs()
+            .map(|(i, j)| self.overlap_area(i, j))
+            .sum()
+    }
 
-        // Loop over all nodes, updating their positions and velocities
-        for i in 0..self.get_n_nodes() {
-            self.nodes[i].position += delta_p[i];
-            self.nodes[i].velocity += delta_v[i];
+    /// Area of the intersection of the circles of the nodes at `i` and `j`.
+    fn overlap_area(&self, i: usize, j: usize) -> f64 {
+        let (r1, r2) = (self.nodes[i].radius, self.nodes[j].radius);
+        let d = self.nodes[i].position.distance(&self.nodes[j].position);
+        if d >= r1 + r2 {
+            return 0.0;
+        }
+        if d <= (r1 - r2).abs() {
+            // One circle lies entirely inside the other
+            return std::f64::consts::PI * r1.min(r2).powi(2);
         }
-    }
-}
 
-#[cfg(test)]
-pub mod test {
-    use super::*;
+        // The lens is a circular segment of each circle
+        let segment1 = r1.powi(2) * ((d * d + r1 * r1 - r2 * r2) / (2.0 * d * r1)).acos();
+        let segment2 = r2.powi(2) * ((d * d + r2 * r2 - r1 * r1) / (2.0 * d * r2)).acos();
+        let kite = 0.5 * ((-d + r1 + r2) * (d + r1 - r2) * (d - r1 + r2) * (d + r1 + r2)).sqrt();
+        segment1 + segment2 - kite
+    }
 
-    fn setup() -> (Vec<Node>, Vec<Edge>) {
-        let node1 = Node::new()
-            .id(1)
-            .label("Node 1")
-            .position(Vector2D::from_xy(0.0, 0.0))
-            .build();
-        let node2 = Node::new()
-            .id(2)
-            .label("Node 2")
-            .position(Vector2D::from_xy(1.0, 0.0))
-            .build();
-        let node3 = Node::new()
-            .id(3)
-            .label("Node 3")
-            .position(Vector2D::from_xy(0.0, 1.0))
-            .build();
-        let nodes: Vec<Node> = vec![node1, node2, node3];
+    /// Samples the force a probe of unit mass, connected to nothing, would feel at each point of
+    /// a `grid` x `grid` lattice over the nodes' bounding box, padded by a margin of 10% of its
+    /// larger side (or 1 if all the nodes coincide). Returns `(position, force)` pairs in row
+    /// order, for drawing the repulsion field as arrows. A probe exactly on a node feels no force
+    /// from it.
+    pub fn sample_force_field(&self, grid: usize) -> Vec<(Vector2D<f64>, Vector2D<f64>)> {
+        let (min, max) = match self.bounding_box() {
+            Some(bounds) if grid > 0 => bounds,
+            _ => return vec![],
+        };
+        let extent = (max.x - min.x).max(max.y - min.y);
+        let margin = if extent > 0.0 { 0.1 * extent } else { 1.0 };
+        let min = min - Vector2D::from_xy(margin, margin);
+        let max = max + Vector2D::from_xy(margin, margin);
 
-        let edge1 = Edge::new(0, 1, 1.0);
-        let edge2 = Edge::new(0, 2, 2.0);
-        let edge3 = Edge::new(1, 2, 3.0);
-        let edges: Vec<Edge> = vec![edge1, edge2, edge3];
+        // A single sample sits in the middle of the box
+        let fraction = |k: usize| {
+            if grid == 1 {
+                0.5
+            } else {
+                k as f64 / (grid - 1) as f64
+            }
+        };
 
-        (nodes, edges)
+        let mut samples = Vec::with_capacity(grid * grid);
+        for row in 0..grid {
+            for column in 0..grid {
+                let probe = Vector2D::from_xy(
+                    min.x + (max.x - min.x) * fraction(column),
+                    min.y + (max.y - min.y) * fraction(row),
+                );
+                let force = self
+                    .nodes
+                    .iter()
+                    .filter(|node| node.position != probe)
+                    .fold(Vector2D::from_xy(0.0, 0.0), |total, node| {
+                        let (distance, direction) = self
+                            .distance_metric
+                            .separation(&node.position, &probe)
+                            .to_polar();
+                        total
+                            - self.repulsive_force_n1_exerts_on_n2(
+                                distance, node.mass, 1.0, direction,
+                            )
+                    });
+                samples.push((probe, force));
+            }
+        }
+        samples
     }
 
-    fn get_force_simulation() -> ForceSimulation {
-        let (nodes, edges) = setup();
-        ForceSimulation::new(nodes, edges, 1.0, 1.0, 1.0)
+    /// Pulls the node at `node_idx` toward `target` with a spring of the given `strength`, applied
+    /// on every step. Unlike pinning, the node is not fixed in place: the anchor is one force among
+    /// the others acting on it.
+    pub fn add_anchor(&mut self, node_idx: usize, target: Vector2D<f64>, strength: f64) {
+        self.anchors.push(Anchor {
+            node_idx,
+            target,
+            strength,
+        });
+        self.invalidate_forces();
     }
 
-    #[test]
-    pub fn test_get_node_mass() {
-        let mut force_simulation = get_force_simulation();
-
-        assert_eq!(force_simulation.get_node_mass(0), 1.0 + 2.0);
-        assert_eq!(force_simulation.get_node_mass(1), 1.0 + 3.0);
-        assert_eq!(force_simulation.get_node_mass(2), 2.0 + 3.0);
+    /// Removes every anchor added with [ForceSimulation::add_anchor](ForceSimulation::add_anchor).
+    pub fn clear_anchors(&mut self) {
+        self.anchors.clear();
+        self.invalidate_forces();
     }
 
-    #[test]
-    pub fn test_repulsive_force_n1_exerts_on_n2() {
-        let mut force_simulation = get_force_simulation();
+    /// Pushes every node within `radius` of `center` away from it, with a force of
+    /// `strength * (radius - distance)` that fades to 0 at the edge of the region. This is the
+    /// repulsive counterpart of [ForceSimulation::add_anchor](ForceSimulation::add_anchor), for
+    /// keeping nodes out of an area of the canvas.
+    pub fn add_obstacle(&mut self, center: Vector2D<f64>, radius: f64, strength: f64) {
+        self.obstacles.push(Obstacle {
+            center,
+            radius,
+            strength,
+        });
+        self.invalidate_forces();
+    }
 
-        let distance = 1.0;
-        let n1_mass = 1.0;
-        let n2_mass = 1.0;
-        let direction = 0.0;
+    /// Removes every obstacle added with
+    /// [ForceSimulation::add_obstacle](ForceSimulation::add_obstacle).
+    pub fn clear_obstacles(&mut self) {
+        self.obstacles.clear();
+        self.invalidate_forces();
+    }
+
+    fn acceleration_from_force_n1_exerts_on_n2(
+        &self,
+        n1: &Node,
+        n2: &Node,
+        weight: f64,
+    ) -> Vector2D<f64> {
+        let total_force = self.total_force_n1_exerts_on_n2(n1, n2, weight);
+        total_force / n2.mass
+    }
+
+    /// The attractive force along an edge. Under [WeightSemantics::Strength] its magnitude is
+    /// `attraction_constant * weight * distance^attraction_exponent`; under
+    /// [WeightSemantics::Distance] it is the spring force `attraction_constant * (distance - weight)`,
+    /// which pushes the nodes apart when they are closer than the weight.
+    ///
+    /// The force points along `direction`, from n2 toward n1, so a positive magnitude pulls n2
+    /// toward n1. Under [WeightSemantics::Strength] a negative weight, such as a negative
+    /// correlation, gives a negative magnitude: a spring of strength `|weight|` pushing the nodes
+    /// apart.
+    fn attractive_force_n1_exerts_on_n2(
+        &self,
+        distance: f64,
+        weight: f64,
+        direction: f64,
+    ) -> Vector2D<f64> {
+        let magnitude = match self.weight_semantics {
+            WeightSemantics::Strength => {
+                self.attraction_constant * weight * distance.powf(self.attraction_exponent)
+            }
+            WeightSemantics::Distance => self.attraction_constant * (distance - weight),
+        };
+        Vector2D::from_rtheta(magnitude, direction)
+    }
+
+    /// Returns the mass-weighted average position of the nodes. If the total mass is 0, this is
+    /// the origin.
+    pub fn center_of_mass(&self) -> Vector2D<f64> {
+        let total_mass: f64 = self.nodes.iter().map(|node| node.mass).sum();
+        if total_mass == 0.0 {
+            return Vector2D::from_xy(0.0, 0.0);
+        }
+        self.nodes
+            .iter()
+            .fold(Vector2D::from_xy(0.0, 0.0), |total, node| {
+                total + node.position * node.mass
+            })
+            / total_mass
+    }
+
+    /// Change in the position of n2 over `delta_time` due to the force n1 exerts on it.
+    fn chg_in_position_from_force_n1_exerts_on_n2(
+        &self,
+        n1: &Node,
+        n2: &Node,
+        weight: f64,
+        delta_time: f64,
+    ) -> Vector2D<f64> {
+        // Only the acceleration term of the kinematics equation belongs to this pair; the v0 * dt
+        // term is added once per node in integrate
+        let a = self.acceleration_from_force_n1_exerts_on_n2(n1, n2, weight);
+        a * delta_time.powi(2) / 2.0
+    }
+
+    fn chg_in_velocity_from_force_n1_exerts_on_n2(
+        &self,
+        n1: &Node,
+        n2: &Node,
+        weight: f64,
+        delta_time: f64,
+    ) -> Vector2D<f64> {
+        let acceleration = self.acceleration_from_force_n1_exerts_on_n2(n1, n2, weight);
+        acceleration * delta_time
+    }
+
+    /// Returns the length of every edge, using the current node positions.
+    fn edge_lengths(&self) -> Vec<f64> {
+        self.edges
+            .iter()
+            .map(|edge| {
+                self.nodes[edge.node1_idx]
+                    .position
+                    .distance(&self.nodes[edge.node2_idx].position)
+            })
+            .collect()
+    }
+
+    /// Returns the (min, mean, max) edge length, using the current node positions. If there are
+    /// no edges, all three are 0.
+    pub fn edge_length_stats(&self) -> (f64, f64, f64) {
+        let lengths = self.edge_lengths();
+        if lengths.is_empty() {
+            return (0.0, 0.0, 0.0);
+        }
+
+        let min = lengths.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = lengths.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let mean = lengths.iter().sum::<f64>() / lengths.len() as f64;
+        (min, mean, max)
+    }
+
+    /// Returns the (population) variance of the edge lengths, using the current node positions.
+    /// If there are no edges, the variance is 0.
+    pub fn edge_length_variance(&self) -> f64 {
+        let lengths = self.edge_lengths();
+        if lengths.is_empty() {
+            return 0.0;
+        }
+
+        let (_, mean, _) = self.edge_length_stats();
+        lengths.iter().map(|l| (l - mean).powi(2)).sum::<f64>() / lengths.len() as f64
+    }
+
+    /// Returns the layout quality metrics at the current node positions in a single report, to
+    /// compare the layouts produced by two parameter settings.
+    ///
+    /// ### See also
+    /// - [ForceSimulation::edge_crossings](ForceSimulation::edge_crossings)
+    /// - [ForceSimulation::overlap_count](ForceSimulation::overlap_count)
+    /// - [ForceSimulation::edge_length_variance](ForceSimulation::edge_length_variance)
+    pub fn layout_quality(&self) -> LayoutQuality {
+        let (_, mean_length, _) = self.edge_length_stats();
+        let edge_length_cv = if mean_length > 0.0 {
+            self.edge_length_variance().sqrt() / mean_length
+        } else {
+            0.0
+        };
+        let min_node_distance = self
+            .node_pairs()
+            .map(|(i, j)| self.nodes[i].position.distance(&self.nodes[j].position))
+            .fold(f64::INFINITY, f64::min);
+
+        LayoutQuality {
+            edge_crossings: self.edge_crossings(),
+            node_overlaps: self.overlap_count(),
+            edge_length_cv,
+            min_node_distance,
+        }
+    }
+
+    /// Returns the key used for a pair of nodes in the edge index. Edges are undirected, so the
+    /// key is the same regardless of the order of the two indices.
+    fn edge_index_key(node1_idx: usize, node2_idx: usize) -> (usize, usize) {
+        (node1_idx.min(node2_idx), node1_idx.max(node2_idx))
+    }
+
+    /// Returns the acceleration of the node at `node_idx` due to forces that do not come from
+    /// other nodes: gravity toward the gravity center, any anchors on the node, and any obstacles
+    /// it is inside.
+    fn external_acceleration(&self, node_idx: usize) -> Vector2D<f64> {
+        let node = &self.nodes[node_idx];
+
+        // Gravity pulls each node toward the gravity center, proportionally to its distance from it
+        let mut acceleration = (self.gravity_center - node.position) * self.gravity;
+
+        for anchor in self.anchors.iter().filter(|a| a.node_idx == node_idx) {
+            let force = (anchor.target - node.position) * anchor.strength;
+            acceleration += force / node.mass;
+        }
+
+        for obstacle in self.obstacles.iter() {
+            let offset = node.position - obstacle.center;
+            let distance = offset.magnitude();
+            if distance < obstacle.radius {
+                // A node at the very center is pushed out along the x axis
+                let outward = if distance > 0.0 {
+                    offset / distance
+                } else {
+                    Vector2D::from_xy(1.0, 0.0)
+                };
+                let force = outward * (obstacle.strength * (obstacle.radius - distance));
+                acceleration += force / node.mass;
+            }
+        }
+        acceleration
+    }
+
+    /// Returns the edge connecting the nodes at `node1_idx` and `node2_idx`, in either order, or
+    /// [Error::MissingEdge] if they are not connected.
+    pub fn edge_between(&self, node1_idx: usize, node2_idx: usize) -> Result<&Edge> {
+        self.get_edge_connecting_nodes(node1_idx, node2_idx)
+            .ok_or(Error::MissingEdge(node1_idx, node2_idx))
+    }
+
+    /// Returns the edge connecting the two nodes, if any, in O(1) using the edge index. If there
+    /// are parallel edges between the two nodes, the first one is returned.
+    fn get_edge_connecting_nodes(&self, node1_idx: usize, node2_idx: usize) -> Option<&Edge> {
+        self.edge_index
+            .get(&Self::edge_index_key(node1_idx, node2_idx))
+            .map(|&edge_idx| &self.edges[edge_idx])
+    }
+
+    pub fn get_edges(&self) -> &Vec<Edge> {
+        &self.edges
+    }
+
+    /// Removes every self-loop and merges each set of parallel edges into its first edge, summing
+    /// their weights, then updates the edge index and the node masses to match. Returns how many
+    /// edges were removed. Imported graphs often contain both, and they inflate the masses of
+    /// their nodes without adding any useful attraction.
+    pub fn clean_edges(&mut self) -> usize {
+        let n_edges = self.edges.len();
+        self.edges.retain(|edge| edge.node1_idx != edge.node2_idx);
+        self.dedup_parallel(EdgeMerge::Sum);
+        n_edges - self.edges.len()
+    }
+
+    /// Merges each set of parallel edges into its first edge, combining their weights as `merge`
+    /// says, then updates the edge index and the node masses to match. Returns how many edges
+    /// were removed. Each parallel edge adds to the masses of its nodes, so an imported multigraph
+    /// would otherwise weigh its nodes down.
+    pub fn dedup_parallel(&mut self, merge: EdgeMerge) -> usize {
+        let n_edges = self.edges.len();
+        let mut first_edge: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut merged: Vec<Edge> = Vec::with_capacity(n_edges);
+        for edge in self.edges.drain(..) {
+            match first_edge.entry(Self::edge_index_key(edge.node1_idx, edge.node2_idx)) {
+                Entry::Occupied(entry) => {
+                    let kept = &mut merged[*entry.get()];
+                    kept.weight = match merge {
+                        EdgeMerge::Sum => kept.weight + edge.weight,
+                        EdgeMerge::Max => kept.weight.max(edge.weight),
+                    };
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(merged.len());
+                    merged.push(edge);
+                }
+            }
+        }
+        self.edges = merged;
+
+        self.update_edge_index();
+        self.update_masses();
+        n_edges - self.edges.len()
+    }
+
+    /// Removes every edge between the nodes at `node1_idx` and `node2_idx`, in either order, then
+    /// updates the edge index and the node masses to match. Returns how many edges were removed.
+    pub fn remove_edges_between(&mut self, node1_idx: usize, node2_idx: usize) -> usize {
+        let n_edges = self.edges.len();
+        let key = Self::edge_index_key(node1_idx, node2_idx);
+        self.edges
+            .retain(|edge| Self::edge_index_key(edge.node1_idx, edge.node2_idx) != key);
+
+        self.update_edge_index();
+        self.update_masses();
+        n_edges - self.edges.len()
+    }
+
+    /// Returns the edges in compressed sparse row form, for consumers that iterate over the
+    /// neighbors of every node and want them contiguous in memory.
+    pub fn to_csr(&self) -> CsrGraph {
+        CsrGraph::from_edges(self.n_nodes(), &self.edges)
+    }
+
+    /// Returns a vector of edges that are connected to the node with the provided index.
+    fn get_edges_by_node_idx(&self, node_idx: usize) -> Vec<&Edge> {
+        let mut edges: Vec<&Edge> = Vec::new();
+        for edge in &self.edges {
+            if (edge.has_node(node_idx)) {
+                edges.push(edge);
+            }
+        }
+        edges
+    }
+
+    /// Returns every edge incident to the node at `node_idx`, in edge order, e.g. to highlight a
+    /// node's connections on hover. A self-loop is returned once. Returns no edges if there is no
+    /// such node.
+    pub fn edges_of(&self, node_idx: usize) -> Vec<&Edge> {
+        self.get_edges_by_node_idx(node_idx)
+    }
+
+    /// Returns the number of edge endpoints at the node at `node_idx`, read from the degree cache
+    /// kept with the edge index. A self-loop counts twice. Returns 0 if there is no such node.
+    pub fn degree(&self, node_idx: usize) -> usize {
+        self.degrees.get(node_idx).copied().unwrap_or(0)
+    }
+
+    /// Returns how many nodes have each degree: entry `d` counts the nodes of degree `d`, up to
+    /// the largest degree. Returns no entries if there are no nodes.
+    pub fn degree_histogram(&self) -> Vec<usize> {
+        let mut histogram = vec![];
+        for &degree in self.degrees.iter() {
+            if histogram.len() <= degree {
+                histogram.resize(degree + 1, 0);
+            }
+            histogram[degree] += 1;
+        }
+        histogram
+    }
+
+    /// Estimates the exponent `gamma` of a power-law degree distribution, where the number of
+    /// nodes of degree `d` falls off as `d^-gamma`, by a least-squares fit of the log of the
+    /// count against the log of the degree. Nodes of degree 0 are left out. Returns `None` if
+    /// fewer than two distinct degrees remain. This is a rough check of whether a graph is
+    /// scale-free, not a rigorous fit.
+    pub fn estimate_power_law_exponent(&self) -> Option<f64> {
+        let points: Vec<(f64, f64)> = self
+            .degree_histogram()
+            .iter()
+            .enumerate()
+            .skip(1)
+            .filter(|&(_, &count)| count > 0)
+            .map(|(degree, &count)| ((degree as f64).ln(), (count as f64).ln()))
+            .collect();
+        if points.len() < 2 {
+            return None;
+        }
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|&(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / n;
+        let covariance: f64 = points
+            .iter()
+            .map(|&(x, y)| (x - mean_x) * (y - mean_y))
+            .sum();
+        let variance: f64 = points.iter().map(|&(x, _)| (x - mean_x).powi(2)).sum();
+        Some(-covariance / variance)
+    }
+
+    /// Returns the nodes that should be drawn, skipping hidden ones. Hidden nodes still take part
+    /// in the simulation, so this is only for render output.
+    pub fn visible_nodes(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.iter().filter(|node| node.visible)
+    }
+
+    /// Returns the node with the provided `id`, or `None` if no node has that id. Note that a
+    /// node's `id` is not necessarily its index in the simulation.
+    pub fn get_node_by_id(&self, id: usize) -> Option<&Node> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    /// Returns the index of the node with the provided `id`, or `None` if no node has that id. If
+    /// several nodes share the id, the first one is returned. The id-to-index map is built on the
+    /// first call, so later lookups are O(1).
+    pub fn node_index_of(&self, id: usize) -> Option<usize> {
+        let index = self.id_index.get_or_init(|| {
+            let mut index = HashMap::new();
+            for (node_idx, node) in self.nodes.iter().enumerate() {
+                index.entry(node.id).or_insert(node_idx);
+            }
+            index
+        });
+        index.get(&id).copied()
+    }
+
+    /// Returns the id of the node at `node_idx`, or `None` if there is no such node. This is the
+    /// inverse of [ForceSimulation::node_index_of](ForceSimulation::node_index_of).
+    pub fn node_id_at(&self, node_idx: usize) -> Option<usize> {
+        self.nodes.get(node_idx).map(|node| node.id)
+    }
+
+    /// Adds an edge of the given `weight` between the nodes with ids `id_a` and `id_b`, looking up
+    /// their indices so callers never have to. The edge index and the node masses are updated to
+    /// match. Returns [Error::UnknownNodeId] if either id belongs to no node.
+    pub fn add_edge_by_id(&mut self, id_a: usize, id_b: usize, weight: f64) -> Result<()> {
+        let node1_idx = self.node_index_of(id_a).ok_or(Error::UnknownNodeId(id_a))?;
+        let node2_idx = self.node_index_of(id_b).ok_or(Error::UnknownNodeId(id_b))?;
+        self.edges.push(Edge::new(node1_idx, node2_idx, weight));
+        self.derive_from_edges();
+        Ok(())
+    }
+
+    /// Connects the nodes with ids `id_a` and `id_b` by an edge of the given `weight`. This is the
+    /// same as [ForceSimulation::add_edge_by_id](ForceSimulation::add_edge_by_id), under a name
+    /// that reads better at call sites.
+    pub fn connect(&mut self, id_a: usize, id_b: usize, weight: f64) -> Result<()> {
+        self.add_edge_by_id(id_a, id_b, weight)
+    }
+
+    fn get_node_mass(&self, node_idx: usize) -> f64 {
+        let mut total_mass: f64 = 0.0;
+        for edge in &self.edges {
+            if edge.node1_idx == node_idx || edge.node2_idx == node_idx {
+                // A negative weight is a repulsive spring, but still adds to the mass
+                total_mass += edge.weight.abs();
+            }
+        }
+        total_mass
+    }
+
+    pub fn get_nodes(&self) -> &Vec<Node> {
+        &self.nodes
+    }
+
+    /// Returns the net force on each node, as computed in the most recent call to
+    /// [ForceSimulation::step](ForceSimulation::step). Before the first step every force is 0.
+    pub fn last_forces(&self) -> &Vec<Vector2D<f64>> {
+        &self.last_forces
+    }
+
+    /// Returns the report of the most recent call to [ForceSimulation::step](ForceSimulation::step),
+    /// or `None` if no step has been taken since the simulation was created or deserialized.
+    pub fn last_report(&self) -> Option<StepReport> {
+        self.last_report
+    }
+
+    /// Whether the layout has settled: the most recent step moved no node `epsilon` or more.
+    /// A simulation that has not stepped yet is not settled.
+    pub fn is_settled(&self, epsilon: f64) -> bool {
+        self.last_report
+            .is_some_and(|report| report.max_displacement < epsilon)
+    }
+
+    /// Returns a compact snapshot of the simulation, with `settled` judged against `epsilon` as
+    /// in [ForceSimulation::is_settled](ForceSimulation::is_settled).
+    pub fn state(&self, epsilon: f64) -> SimulationState {
+        SimulationState {
+            step_count: self.steps_taken,
+            settled: self.is_settled(epsilon),
+            kinetic_energy: self.kinetic_energy(),
+            positions: self.export_positions(),
+        }
+    }
+
+    /// Returns the number of nodes in the simulation.
+    pub fn n_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Returns the number of edges in the simulation.
+    pub fn n_edges(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Boolean to test if the simulation has no nodes.
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Returns every unordered pair of node indices `(i, j)` with `i < j`, each exactly once. Use
+    /// this rather than nested index loops to visit every pair of nodes.
+    pub fn node_pairs(&self) -> impl Iterator<Item = (usize, usize)> {
+        let n_nodes = self.n_nodes();
+        (0..n_nodes).flat_map(move |i| ((i + 1)..n_nodes).map(move |j| (i, j)))
+    }
+
+    /// Returns the current position of the node with the provided `id`, or `None` if no node has
+    /// that id.
+    pub fn position_of(&self, id: usize) -> Option<Vector2D<f64>> {
+        self.get_node_by_id(id).map(|node| node.position)
+    }
+
+    /// Returns the layout as `(id, x, y)` triples, one per node in index order, so it can be
+    /// saved and later restored with
+    /// [ForceSimulation::import_positions](ForceSimulation::import_positions) without re-running
+    /// the simulation.
+    pub fn export_positions(&self) -> Vec<(usize, f64, f64)> {
+        self.nodes
+            .iter()
+            .map(|node| (node.id, node.position.x, node.position.y))
+            .collect()
+    }
+
+    /// Compares this layout with `other`, returning `(id, distance)` for every node, in index
+    /// order, with how far it sits from the node with the same id in `other`. Nodes whose id is
+    /// not in `other` are left out. This is meant for regression tests against a known-good
+    /// layout.
+    ///
+    /// ### See also
+    /// [ForceSimulation::max_position_diff](ForceSimulation::max_position_diff)
+    pub fn diff(&self, other: &ForceSimulation) -> Vec<(usize, f64)> {
+        let other_positions: HashMap<usize, Vector2D<f64>> = other
+            .nodes
+            .iter()
+            .rev()
+            .map(|node| (node.id, node.position))
+            .collect();
+        self.nodes
+            .iter()
+            .filter_map(|node| {
+                let other_position = other_positions.get(&node.id)?;
+                Some((node.id, node.position.distance(other_position)))
+            })
+            .collect()
+    }
+
+    /// Returns the largest distance in [ForceSimulation::diff](ForceSimulation::diff), or 0 if
+    /// the two simulations share no node ids.
+    pub fn max_position_diff(&self, other: &ForceSimulation) -> f64 {
+        self.diff(other)
+            .into_iter()
+            .map(|(_, distance)| distance)
+            .fold(0.0, f64::max)
+    }
+
+    /// Returns `(index, x, y)` for every node that has moved more than `threshold` since its
+    /// position was last returned by this method, and records those positions as sent. A renderer
+    /// that keeps the previous frame only needs these updates, which shrink to nothing as the
+    /// layout settles. Small moves are not lost: they accumulate until they exceed the threshold.
+    /// The first call, and any call after nodes are added or removed, returns every node.
+    pub fn position_delta(&mut self, threshold: f64) -> Vec<(usize, f64, f64)> {
+        if self.last_sent_positions.len() != self.n_nodes() {
+            self.last_sent_positions = self.nodes.iter().map(|node| node.position).collect();
+            return self.export_positions_by_index();
+        }
+
+        let mut delta = vec![];
+        for (idx, node) in self.nodes.iter().enumerate() {
+            if node.position.distance(&self.last_sent_positions[idx]) > threshold {
+                self.last_sent_positions[idx] = node.position;
+                delta.push((idx, node.position.x, node.position.y));
+            }
+        }
+        delta
+    }
+
+    fn export_positions_by_index(&self) -> Vec<(usize, f64, f64)> {
+        self.nodes
+            .iter()
+            .enumerate()
+            .map(|(idx, node)| (idx, node.position.x, node.position.y))
+            .collect()
+    }
+
+    /// Moves each node to the position given for its id in `positions`, as exported by
+    /// [ForceSimulation::export_positions](ForceSimulation::export_positions). Ids that match no
+    /// node are ignored, and nodes whose id is missing from `positions` keep their current
+    /// position. Velocities are left unchanged.
+    pub fn import_positions(&mut self, positions: &[(usize, f64, f64)]) {
+        let by_id: HashMap<usize, Vector2D<f64>> = positions
+            .iter()
+            .map(|&(id, x, y)| (id, Vector2D::from_xy(x, y)))
+            .collect();
+        for node in self.nodes.iter_mut() {
+            if let Some(&position) = by_id.get(&node.id) {
+                node.position = position;
+            }
+        }
+        self.update_positions();
+    }
+
+    /// Rotates the nodes at `indices` by `angle` radians around `pivot`, as when a user rotates a
+    /// selection of nodes. Velocities and all other nodes are left unchanged.
+    pub fn rotate_nodes(&mut self, indices: &[usize], angle: f64, pivot: Vector2D<f64>) {
+        for &idx in indices {
+            let node = &mut self.nodes[idx];
+            node.position = node.position.rotate_around(angle, &pivot);
+        }
+        self.update_positions();
+    }
+
+    /// Applies the same affine transform to every node position: scaling about the origin by
+    /// `scale`, then rotating about the origin by `rotation` radians, then translating by
+    /// `translation`. This is how a viewport controller pans, zooms and turns the whole layout.
+    /// Velocities are left unchanged.
+    pub fn apply_affine(&mut self, scale: f64, rotation: f64, translation: Vector2D<f64>) {
+        for node in self.nodes.iter_mut() {
+            node.position = node.position.scale(scale).rotate(rotation) + translation;
+        }
+        self.update_positions();
+    }
+
+    /// Rescales and translates every node so the layout's bounding box fits the `target` box,
+    /// as the last step before drawing into a known viewport. The corners of `target` are matched
+    /// as in [Vector2D::remap](Vector2D::remap), so either axis can be flipped.
+    ///
+    /// ### Parameters
+    /// - `target`: Corners of the box to fit the layout into
+    /// - `preserve_aspect`: If false, each axis is stretched to fill the box exactly. If true,
+    ///   both axes are scaled by the same factor so the layout fits the box along its limiting
+    ///   axis, and it is centered along the other.
+    ///
+    /// ### Notes
+    /// Velocities are left unchanged. A layout with zero extent along an axis is placed in the
+    /// middle of the box along that axis.
+    pub fn normalize_layout(
+        &mut self,
+        target: (Vector2D<f64>, Vector2D<f64>),
+        preserve_aspect: bool,
+    ) {
+        let from = match self.bounding_box() {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let to = if preserve_aspect {
+            let extent = from.1 - from.0;
+            let target_extent = target.1 - target.0;
+            let scale = [(extent.x, target_extent.x), (extent.y, target_extent.y)]
+                .iter()
+                .filter(|(length, _)| *length > 0.0)
+                .map(|(length, target_length)| target_length.abs() / length)
+                .fold(f64::INFINITY, f64::min);
+            let scale = if scale.is_finite() { scale } else { 0.0 };
+
+            // A box the shape of the layout, centered in the target with the same orientation
+            let center = (target.0 + target.1) / 2.0;
+            let half = Vector2D::from_xy(
+                target_extent.x.signum() * extent.x * scale / 2.0,
+                target_extent.y.signum() * extent.y * scale / 2.0,
+            );
+            (center - half, center + half)
+        } else {
+            target
+        };
+
+        for node in self.nodes.iter_mut() {
+            node.position = node.position.remap(from, to);
+        }
+        self.update_positions();
+    }
+
+    /// Sets the velocity of the node at `node_idx`, e.g. to kick off a burst animation. This
+    /// wakes the node if it is asleep.
+    pub fn set_velocity(&mut self, node_idx: usize, v: Vector2D<f64>) {
+        self.nodes[node_idx].velocity = v;
+        self.velocities[node_idx] = v;
+        self.wake(node_idx);
+    }
+
+    /// Lets nodes fall asleep: a node whose speed stays below `speed` for `steps` consecutive
+    /// steps stops being integrated, saving the work of moving nodes that have settled, until a
+    /// neighbor moving at `speed` or more wakes it. A sleeping node still exerts forces on every
+    /// other node. Every node starts awake.
+    ///
+    /// ### See also
+    /// [ForceSimulation::disable_sleep](ForceSimulation::disable_sleep)
+    pub fn set_sleep_threshold(&mut self, speed: f64, steps: usize) {
+        self.sleep_threshold = Some((speed, steps));
+        self.still_steps = vec![0; self.n_nodes()];
+        self.asleep = vec![false; self.n_nodes()];
+    }
+
+    /// Wakes every node and stops nodes from falling asleep.
+    pub fn disable_sleep(&mut self) {
+        self.sleep_threshold = None;
+        self.still_steps.clear();
+        self.asleep.clear();
+    }
+
+    /// Returns the number of nodes that are not asleep.
+    pub fn awake_count(&self) -> usize {
+        self.n_nodes() - self.asleep.iter().filter(|&&asleep| asleep).count()
+    }
+
+    /// Whether the integrator should leave the node at `node_idx` where it is.
+    fn is_held(&self, node_idx: usize) -> bool {
+        self.nodes[node_idx].fixed || self.asleep.get(node_idx).copied().unwrap_or(false)
+    }
+
+    fn wake(&mut self, node_idx: usize) {
+        if let Some(asleep) = self.asleep.get_mut(node_idx) {
+            *asleep = false;
+            self.still_steps[node_idx] = 0;
+        }
+    }
+
+    /// Counts how long each node has been still, putting to sleep those still for long enough,
+    /// then wakes the sleeping neighbors of every node still moving.
+    fn update_sleep(&mut self) {
+        let Some((speed, steps)) = self.sleep_threshold else {
+            return;
+        };
+        // Nodes added since sleeping was enabled start awake
+        self.still_steps.resize(self.n_nodes(), 0);
+        self.asleep.resize(self.n_nodes(), false);
+
+        let mut moving = std::mem::take(&mut self.scratch.moving);
+        moving.clear();
+        moving.extend(
+            self.nodes
+                .iter()
+                .map(|node| node.velocity.magnitude() >= speed),
+        );
+        for (i, &is_moving) in moving.iter().enumerate() {
+            if self.asleep[i] || self.nodes[i].fixed {
+                continue;
+            }
+            if is_moving {
+                self.still_steps[i] = 0;
+            } else {
+                self.still_steps[i] += 1;
+                if self.still_steps[i] >= steps {
+                    self.asleep[i] = true;
+                    self.nodes[i].velocity = Vector2D::from_xy(0.0, 0.0);
+                }
+            }
+        }
+
+        for edge in self.edges.iter() {
+            for (from, to) in [
+                (edge.node1_idx, edge.node2_idx),
+                (edge.node2_idx, edge.node1_idx),
+            ] {
+                if moving[from] && self.asleep[to] {
+                    self.asleep[to] = false;
+                    self.still_steps[to] = 0;
+                }
+            }
+        }
+        self.scratch.moving = moving;
+    }
+
+    /// Sets the velocity of every node to `v`.
+    pub fn set_all_velocities(&mut self, v: Vector2D<f64>) {
+        for node in self.nodes.iter_mut() {
+            node.velocity = v;
+        }
+        self.update_velocities();
+    }
+
+    /// Fixes or releases the nodes at `indices`. A fixed node keeps its position, and has no
+    /// velocity, until it is released or moved explicitly, e.g. with
+    /// [ForceSimulation::translate_fixed](ForceSimulation::translate_fixed).
+    pub fn set_fixed(&mut self, indices: &[usize], fixed: bool) {
+        for &idx in indices {
+            self.nodes[idx].fixed = fixed;
+            if fixed {
+                self.nodes[idx].velocity = Vector2D::from_xy(0.0, 0.0);
+            }
+        }
+        self.update_velocities();
+    }
+
+    /// Runs `steps` steps in which only the nodes within `hops` edges of the node at `node_idx`
+    /// move, holding every other node fixed. After a local edit, such as adding a node, this
+    /// settles the neighborhood of the edit far faster than relaxing the whole layout. Nodes that
+    /// were already fixed stay fixed, and the rest are released afterwards with their velocities
+    /// intact.
+    ///
+    /// ### Panics
+    /// Panics if there is no node at `node_idx`.
+    pub fn relax_around(&mut self, node_idx: usize, hops: usize, steps: usize) {
+        // Breadth-first search out to the given number of hops
+        let csr = self.to_csr();
+        let mut nearby = vec![false; self.n_nodes()];
+        nearby[node_idx] = true;
+        let mut frontier = vec![node_idx];
+        for _ in 0..hops {
+            let mut next = vec![];
+            for &i in frontier.iter() {
+                for &j in csr.neighbors(i) {
+                    if !nearby[j] {
+                        nearby[j] = true;
+                        next.push(j);
+                    }
+                }
+            }
+            frontier = next;
+        }
+
+        let held: Vec<usize> = (0..self.n_nodes())
+            .filter(|&i| !nearby[i] && !self.nodes[i].fixed)
+            .collect();
+        for &i in held.iter() {
+            self.nodes[i].fixed = true;
+        }
+        self.step_n(steps);
+        for &i in held.iter() {
+            self.nodes[i].fixed = false;
+        }
+    }
+
+    /// Moves the nodes at `indices` rigidly by `delta`, as when a user drags a selection of
+    /// fixed nodes. Call it once per frame between steps, and the rest of the layout relaxes
+    /// around the selection.
+    pub fn translate_fixed(&mut self, indices: &[usize], delta: Vector2D<f64>) {
+        for &idx in indices {
+            self.nodes[idx].position += delta;
+        }
+        self.update_positions();
+    }
+
+    /// The repulsive force between every pair of nodes, with magnitude
+    /// `repulsion_constant * n1_mass * n2_mass * distance^repulsion_exponent`, or 0 beyond the
+    /// repulsion cutoff.
+    ///
+    /// Like the attractive force, this points along `direction`, from n2 toward n1. It is
+    /// subtracted from the attractive force in
+    /// [ForceSimulation::total_force_n1_exerts_on_n2](ForceSimulation::total_force_n1_exerts_on_n2),
+    /// so it pushes n2 away from n1.
+    fn repulsive_force_n1_exerts_on_n2(
+        &self,
+        distance: f64,
+        n1_mass: f64,
+        n2_mass: f64,
+        direction: f64,
+    ) -> Vector2D<f64> {
+        if self.is_beyond_repulsion_cutoff(distance) {
+            return Vector2D::from_xy(0.0, 0.0);
+        }
+        let magnitude =
+            self.repulsion_constant * n1_mass * n2_mass * distance.powf(self.repulsion_exponent);
+        Vector2D::from_rtheta(magnitude, direction)
+    }
+
+    /// Boolean to test if nodes `distance` apart are too far apart to repel each other.
+    fn is_beyond_repulsion_cutoff(&self, distance: f64) -> bool {
+        self.repulsion_cutoff
+            .is_some_and(|cutoff| distance > cutoff)
+    }
+
+    /// The force node n1 exerts on node n2, joined by an edge of the given weight (0 if they are
+    /// not connected). The sign convention is `attractive - repulsive`, with both forces pointing
+    /// from n2 toward n1: connected nodes attract, every pair of nodes repels, and the force on n1
+    /// is the negative of this.
+    fn total_force_n1_exerts_on_n2(&self, n1: &Node, n2: &Node, weight: f64) -> Vector2D<f64> {
+        self.scaled_force_n1_exerts_on_n2(n1, n2, weight, 1.0)
+    }
+
+    /// Same as [ForceSimulation::total_force_n1_exerts_on_n2](ForceSimulation::total_force_n1_exerts_on_n2),
+    /// with the repulsion multiplied by `repulsion_scale`.
+    fn scaled_force_n1_exerts_on_n2(
+        &self,
+        n1: &Node,
+        n2: &Node,
+        weight: f64,
+        repulsion_scale: f64,
+    ) -> Vector2D<f64> {
+        let (distance, direction) = self
+            .distance_metric
+            .separation(&n1.position, &n2.position)
+            .to_polar();
+        let distance = distance.max(self.min_distance);
+        let repulsive_force = if n1.repels(n2) {
+            self.repulsive_force_n1_exerts_on_n2(distance, n1.mass, n2.mass, direction)
+                * repulsion_scale
+        } else {
+            Vector2D::from_xy(0.0, 0.0)
+        };
+        let attractive_force = self.attractive_force_n1_exerts_on_n2(distance, weight, direction);
+
+        // Nodes in the same group attract more strongly, and nodes in different groups repel more
+        // strongly. Nodes without a group are unaffected.
+        match (n1.group, n2.group) {
+            (Some(g1), Some(g2)) if g1 == g2 => {
+                attractive_force * self.intra_group_strength - repulsive_force
+            }
+            (Some(_), Some(_)) => attractive_force - repulsive_force * self.inter_group_strength,
+            _ => attractive_force - repulsive_force,
+        }
+    }
+
+    /// Sets the smallest distance the pairwise forces and potentials are evaluated at, 1e-5 by
+    /// default. Nodes closer together than this, including coincident nodes, are treated as this
+    /// far apart, which keeps the forces between them finite. Raise it for layouts whose
+    /// coordinates are large, or lower it for very small-scale coordinate systems.
+    pub fn set_min_distance(&mut self, min_distance: f64) {
+        self.min_distance = min_distance;
+        self.invalidate_forces();
+    }
+
+    /// Sets the point gravity pulls every node toward, the origin by default. When the layout is
+    /// drawn straight into a viewport, this is usually the center of the viewport.
+    pub fn set_gravity_center(&mut self, center: Vector2D<f64>) {
+        self.gravity_center = center;
+        self.invalidate_forces();
+    }
+
+    /// Enables repulsion between edges that share no node with the given settings, or disables
+    /// it with `None`.
+    pub fn set_edge_repulsion(&mut self, edge_repulsion: Option<EdgeRepulsion>) {
+        self.edge_repulsion = edge_repulsion;
+        self.invalidate_forces();
+    }
+
+    /// Sets how strongly group membership scales the pairwise forces: attraction between nodes in
+    /// the same group is multiplied by `intra`, and repulsion between nodes in different groups
+    /// is multiplied by `inter`. Both default to 1, which ignores groups entirely.
+    pub fn set_group_strength(&mut self, intra: f64, inter: f64) {
+        self.intra_group_strength = intra;
+        self.inter_group_strength = inter;
+        self.invalidate_forces();
+    }
+
+    /// Rebuilds the edge index mapping each pair of connected nodes to the index of the first
+    /// edge between them, and the number of edges incident to each node. This must be called
+    /// whenever the edges change.
+    pub fn update_edge_index(&mut self) {
+        self.edge_index.clear();
+        self.degrees = vec![0; self.n_nodes()];
+        self.invalidate_forces();
+        for (edge_idx, edge) in self.edges.iter().enumerate() {
+            self.edge_index
+                .entry(Self::edge_index_key(edge.node1_idx, edge.node2_idx))
+                .or_insert(edge_idx);
+            for node_idx in [edge.node1_idx, edge.node2_idx] {
+                if let Some(degree) = self.degrees.get_mut(node_idx) {
+                    *degree += 1;
+                }
+            }
+        }
+    }
+
+    /// Factor the repulsion of the node at `node_idx` is scaled by: `1 + ln(degree)` with
+    /// degree-scaled repulsion, so a node with a single edge is unaffected, and 1 otherwise.
+    fn repulsion_scale(&self, node_idx: usize) -> f64 {
+        match self.degrees.get(node_idx) {
+            Some(&degree) if self.degree_scaled_repulsion && degree > 0 => {
+                1.0 + (degree as f64).ln()
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Returns the total potential energy of the current layout, summed over every pair of nodes.
+    /// The potentials are the ones the force functions are derived from: a force following
+    /// `distance^exponent` has the potential `distance^(exponent + 1) / (exponent + 1)`, or
+    /// `ln(distance)` for an exponent of -1. With the default exponents, these are:
+    ///
+    /// - repulsion between every pair, inverse-square: `repulsion_constant * m1 * m2 / distance`
+    /// - attraction along each edge, a linear spring:
+    ///   `attraction_constant * weight * distance^2 / 2`
+    ///
+    /// Under [WeightSemantics::Distance], the attraction along each edge is instead the spring
+    /// potential `attraction_constant * (distance - weight)^2 / 2`.
+    ///
+    /// This excludes kinetic energy, so it measures the quality of the layout itself.
+    pub fn system_energy(&self) -> f64 {
+        let mut energy = 0.0;
+        for (i, j) in self.node_pairs() {
+            let distance = self
+                .distance_metric
+                .distance(&self.nodes[i].position, &self.nodes[j].position)
+                .max(self.min_distance);
+
+            // The repulsive potential is flat beyond the cutoff, where the force vanishes
+            let repulsion_distance = self
+                .repulsion_cutoff
+                .map_or(distance, |cutoff| distance.min(cutoff));
+            if self.nodes[i].repels(&self.nodes[j]) {
+                energy -= self.repulsion_constant
+                    * self.nodes[i].mass
+                    * self.nodes[j].mass
+                    * Self::power_law_potential(repulsion_distance, self.repulsion_exponent);
+            }
+            if let Some(edge) = self.get_edge_connecting_nodes(i, j) {
+                energy += match self.weight_semantics {
+                    WeightSemantics::Strength => {
+                        self.attraction_constant
+                            * edge.weight
+                            * Self::power_law_potential(distance, self.attraction_exponent)
+                    }
+                    WeightSemantics::Distance => {
+                        self.attraction_constant * (distance - edge.weight).powi(2) / 2.0
+                    }
+                };
+            }
+        }
+        energy
+    }
+
+    /// Antiderivative of `distance^exponent`, the potential of a unit force following that power
+    /// law. The exponent -1 integrates to a logarithm.
+    fn power_law_potential(distance: f64, exponent: f64) -> f64 {
+        if exponent == -1.0 {
+            distance.ln()
+        } else {
+            distance.powf(exponent + 1.0) / (exponent + 1.0)
+        }
+    }
+
+    /// Returns the total kinetic energy of the nodes, `sum(mass * |velocity|^2 / 2)`. Together with
+    /// [ForceSimulation::system_energy](ForceSimulation::system_energy), this is the total energy
+    /// of the system, which an undamped simulation should conserve.
+    pub fn kinetic_energy(&self) -> f64 {
+        self.nodes
+            .iter()
+            .map(|node| node.mass * node.velocity.dot(&node.velocity) / 2.0)
+            .sum()
+    }
+
+    /// Updates the positions cache based on the current positions of the nodes.
+    pub fn update_positions(&mut self) {
+        for i in 0..self.nodes.len() {
+            self.positions[i] = self.nodes[i].position;
+        }
+    }
+
+    /// Updates the velocities cache based on the current velocities of the nodes.
+    pub fn update_velocities(&mut self) {
+        for i in 0..self.nodes.len() {
+            self.velocities[i] = self.nodes[i].velocity;
+        }
+    }
+
+    /// Sets each node's mass to the sum of the absolute weights of its incident edges, and updates
+    /// the masses cache to match. A node with no incident edges keeps its current mass, since a
+    /// mass of 0 would make its acceleration undefined.
+    pub fn update_masses(&mut self) {
+        for i in 0..self.nodes.len() {
+            let edge_mass = self.get_node_mass(i);
+            if edge_mass != 0.0 {
+                self.nodes[i].update_mass(edge_mass);
+            }
+            self.masses[i] = self.nodes[i].mass;
+        }
+        self.invalidate_forces();
+    }
+
+    /// Discards the net forces kept from the end of the last velocity-Verlet step, after a change
+    /// other than a move of the nodes that alters the forces.
+    fn invalidate_forces(&mut self) {
+        self.scratch.verlet_positions.clear();
+    }
+
+    /// Whether the net forces computed at the end of the last velocity-Verlet step still hold, so
+    /// the next step can start from them instead of computing the same forces again.
+    fn reusable_forces(&self) -> bool {
+        self.integrator == Integrator::VelocityVerlet
+            && !self.scratch.verlet_positions.is_empty()
+            && self.scratch.verlet_positions.len() == self.n_nodes()
+            && self
+                .nodes
+                .iter()
+                .zip(self.scratch.verlet_positions.iter())
+                .all(|(node, position)| node.position == *position)
+    }
+
+    /// Performs a single simulation step, returning how far the nodes moved. Callers that only
+    /// need the side effect can ignore the report.
+    pub fn step(&mut self) -> StepReport {
+        let mut forces = std::mem::take(&mut self.scratch.forces);
+        if self.reusable_forces() {
+            // The forces at the end of the last step were computed at these very positions
+            std::mem::swap(&mut forces, &mut self.scratch.verlet_forces);
+        } else {
+            let mut pairwise = std::mem::take(&mut self.scratch.pairwise);
+            self.compute_forces_into(&mut pairwise, &mut forces);
+            self.scratch.pairwise = pairwise;
+        }
+        self.integrate(&forces);
+        // The old forces' buffer becomes the scratch buffer for the next step
+        std::mem::swap(&mut self.last_forces, &mut forces);
+        self.scratch.forces = forces;
+
+        let (max_displacement, total_movement) = self
+            .nodes
+            .iter()
+            .zip(self.scratch.previous_positions.iter())
+            // On a torus, a node that crosses the seam has only moved the short way around
+            .map(|(node, previous)| self.distance_metric.distance(&node.position, previous))
+            .fold((0.0, 0.0), |(max, total), displacement| {
+                (f64::max(max, displacement), total + displacement)
+            });
+        let report = StepReport {
+            max_displacement,
+            total_movement,
+            kinetic_energy: self.kinetic_energy(),
+            temperature: self.temperature,
+        };
+        self.last_report = Some(report);
+        report
+    }
+
+    /// Performs one step, then writes the new positions into `out` as `[x0, y0, x1, y1, ...]`,
+    /// in node index order. Reusing one buffer across frames avoids allocating a new one each
+    /// frame, and a buffer shared with a renderer avoids copying the positions at all. Values of
+    /// `out` past the first `2 * n_nodes` are left unchanged.
+    ///
+    /// Returns [Error::BufferTooSmall] without stepping if `out` holds fewer than `2 * n_nodes`
+    /// values.
+    pub fn step_into(&mut self, out: &mut [f64]) -> Result<StepReport> {
+        let needed = 2 * self.n_nodes();
+        if out.len() < needed {
+            return Err(Error::BufferTooSmall(needed, out.len()));
+        }
+        let report = self.step();
+        for (xy, node) in out.chunks_exact_mut(2).zip(self.nodes.iter()) {
+            xy[0] = node.position.x;
+            xy[1] = node.position.y;
+        }
+        Ok(report)
+    }
+
+    /// Eases the simulation in over its first `n` steps, with the time step ramping up linearly
+    /// from `time_step / (n + 1)` to the full time step. This keeps nodes that start very close
+    /// together from being flung apart by the huge forces between them on the first steps.
+    pub fn warmup_steps(&mut self, n: usize) {
+        self.warmup_steps = n;
+    }
+
+    /// Returns the time step for the next step under the given net `forces`. It is reduced during
+    /// the warmup and, with the automatic time step, capped at the stable time step.
+    fn current_time_step(&self, forces: &[Vector2D<f64>]) -> f64 {
+        let time_step = if self.steps_taken < self.warmup_steps {
+            self.time_step * (self.steps_taken + 1) as f64 / (self.warmup_steps + 1) as f64
+        } else {
+            self.time_step
+        };
+        if self.auto_time_step {
+            time_step.min(self.stable_time_step(forces))
+        } else {
+            time_step
+        }
+    }
+
+    /// Estimates the largest time step that is safe at the current positions and velocities: the
+    /// one that keeps every node's displacement `|v| * dt + |F| / m * dt^2 / 2` under a tenth of
+    /// the smallest distance between two nodes. A large `time_step` on a tightly packed layout
+    /// moves nodes past each other in a single step, which is what makes a simulation diverge.
+    /// Returns the configured time step if no node would move at all.
+    pub fn suggested_time_step(&self) -> f64 {
+        let stable = self.stable_time_step(&self.compute_forces());
+        if stable.is_finite() {
+            stable
+        } else {
+            self.time_step
+        }
+    }
+
+    /// The largest time step that keeps every node's displacement under the given net `forces`
+    /// below a tenth of the smallest spacing between nodes, or infinity if nothing would move.
+    fn stable_time_step(&self, forces: &[Vector2D<f64>]) -> f64 {
+        let min_spacing = self
+            .node_pairs()
+            .map(|(i, j)| {
+                self.distance_metric
+                    .distance(&self.nodes[i].position, &self.nodes[j].position)
+            })
+            .fold(f64::INFINITY, f64::min)
+            .max(self.min_distance);
+        if !min_spacing.is_finite() {
+            return f64::INFINITY;
+        }
+        let max_displacement = 0.1 * min_spacing;
+
+        self.nodes
+            .iter()
+            .zip(forces)
+            .filter(|(node, _)| !node.fixed)
+            .map(|(node, force)| {
+                // Solve |v| * dt + a * dt^2 / 2 = max_displacement for dt
+                let speed = node.velocity.magnitude();
+                let acceleration = force.magnitude() / node.mass;
+                if acceleration > 0.0 {
+                    ((speed * speed + 2.0 * acceleration * max_displacement).sqrt() - speed)
+                        / acceleration
+                } else if speed > 0.0 {
+                    max_displacement / speed
+                } else {
+                    f64::INFINITY
+                }
+            })
+            .fold(f64::INFINITY, f64::min)
+    }
+
+    /// Keeps every node inside the rectangle with the given corners. After each step, a node that
+    /// left the rectangle is moved back to its edge, and the velocity component that carried it
+    /// out is reflected.
+    pub fn set_bounds(&mut self, top_left: Vector2D<f64>, bottom_right: Vector2D<f64>) {
+        let min = Vector2D::from_xy(
+            top_left.x.min(bottom_right.x),
+            top_left.y.min(bottom_right.y),
+        );
+        let max = Vector2D::from_xy(
+            top_left.x.max(bottom_right.x),
+            top_left.y.max(bottom_right.y),
+        );
+        self.bounds = Some((min, max));
+    }
+
+    /// Removes the rectangle set by [ForceSimulation::set_bounds](ForceSimulation::set_bounds).
+    pub fn clear_bounds(&mut self) {
+        self.bounds = None;
+    }
+
+    /// Requires the net force on each node to be summed in a fixed order, by source node index, so
+    /// that layouts are bitwise reproducible.
+    ///
+    /// ### See also
+    /// [SimParams::deterministic](SimParams::deterministic)
+    pub fn set_deterministic(&mut self, enabled: bool) {
+        self.deterministic = enabled;
+    }
+
+    /// Returns whether the net forces are summed in a fixed order.
+    pub fn is_deterministic(&self) -> bool {
+        self.deterministic
+    }
+
+    /// When enabled, the center of mass of the layout is held fixed: after each step, every node
+    /// is translated back by however much the center of mass moved. This keeps the layout
+    /// centered without adding any gravity. Fixed nodes stay put, so the other nodes are
+    /// translated further to make up for them.
+    pub fn lock_centroid(&mut self, enabled: bool) {
+        self.lock_centroid = enabled;
+    }
+
+    /// When enabled, the layout is kept from spinning as a rigid body: after each step, the net
+    /// angular velocity of the movable nodes about their center of mass is found and the
+    /// matching rigid rotation is subtracted from their velocities. Together with
+    /// [ForceSimulation::lock_centroid](ForceSimulation::lock_centroid) this makes a converged
+    /// layout look stationary.
+    pub fn remove_angular_momentum(&mut self, enabled: bool) {
+        self.remove_angular_momentum = enabled;
+    }
+
+    /// Subtracts the rigid rotation about their center of mass from the velocities of the
+    /// movable nodes, leaving them with no net angular momentum.
+    fn cancel_rotation(&mut self) {
+        let movable = || self.nodes.iter().filter(|node| !node.fixed);
+        let total_mass: f64 = movable().map(|node| node.mass).sum();
+        if total_mass == 0.0 {
+            return;
+        }
+        let center = movable().fold(Vector2D::from_xy(0.0, 0.0), |total, node| {
+            total + node.position * node.mass
+        }) / total_mass;
+
+        // omega = L / I, for the angular momentum L and moment of inertia I about the center
+        let (momentum, inertia) = movable().fold((0.0, 0.0), |(momentum, inertia), node| {
+            let arm = node.position - center;
+            (
+                momentum + node.mass * arm.cross(&node.velocity),
+                inertia + node.mass * arm.dot(&arm),
+            )
+        });
+        if inertia == 0.0 {
+            return;
+        }
+        let omega = momentum / inertia;
+        for node in self.nodes.iter_mut().filter(|node| !node.fixed) {
+            node.velocity -= (node.position - center).orthogonal() * omega;
+        }
+    }
+
+    /// Moves any node outside the bounds back inside, reflecting its velocity off the wall.
+    fn apply_bounds(&mut self) {
+        let Some((min, max)) = self.bounds else {
+            return;
+        };
+        for node in self.nodes.iter_mut() {
+            let clamped = node.position.clamp(&min, &max);
+            if clamped.x != node.position.x {
+                node.velocity = node.velocity.reflect(&Vector2D::from_xy(1.0, 0.0));
+            }
+            if clamped.y != node.position.y {
+                node.velocity = node.velocity.reflect(&Vector2D::from_xy(0.0, 1.0));
+            }
+            node.position = clamped;
+        }
+    }
+
+    /// Performs `count` simulation steps.
+    pub fn step_n(&mut self, count: usize) {
+        for _ in 0..count {
+            self.step();
+        }
+    }
+
+    /// Returns an endless iterator that performs one step each time it is advanced, yielding the
+    /// step's report. Bound it with an adapter, e.g. `.take(100)` for a fixed number of steps or
+    /// `.take_while(|report| report.max_displacement > epsilon)` to run until settled.
+    pub fn steps(&mut self) -> impl Iterator<Item = StepReport> + '_ {
+        std::iter::repeat_with(move || self.step())
+    }
+
+    /// Steps until the layout settles, meaning no node moved `epsilon` or more in the last step,
+    /// or until `deadline` passes, whichever comes first. Returns the number of steps taken. The
+    /// deadline is checked before every step, so this returns at most one step's duration late,
+    /// with the best layout reached by then; a deadline already in the past takes no steps.
+    pub fn run_until(&mut self, deadline: Instant, epsilon: f64) -> usize {
+        let mut steps = 0;
+        while Instant::now() < deadline {
+            let report = self.step();
+            steps += 1;
+            if report.max_displacement < epsilon {
+                break;
+            }
+        }
+        steps
+    }
+
+    /// Returns the magnitude of the largest net force on any movable node. At equilibrium every
+    /// net force vanishes, so this measures convergence directly, where a small displacement may
+    /// only mean a small time step or heavy damping. Returns 0 if every node is fixed.
+    pub fn max_net_force(&self) -> f64 {
+        self.compute_forces()
+            .iter()
+            .zip(self.nodes.iter())
+            .filter(|(_, node)| !node.fixed)
+            .map(|(force, _)| force.magnitude())
+            .fold(0.0, f64::max)
+    }
+
+    /// Steps until the layout is balanced, meaning the largest net force on a movable node is
+    /// below `tolerance`, or until `max_steps` steps have been taken. Returns the number of steps
+    /// taken, which is 0 for a layout already balanced.
+    ///
+    /// ### See also
+    /// [ForceSimulation::max_net_force](ForceSimulation::max_net_force)
+    pub fn run_until_balanced(&mut self, tolerance: f64, max_steps: usize) -> usize {
+        let mut steps = 0;
+        while steps < max_steps && self.max_net_force() >= tolerance {
+            self.step();
+            steps += 1;
+        }
+        steps
+    }
+
+    /// Returns the net force on every node at the current positions: the sum of the pairwise
+    /// forces every other node exerts on it, plus gravity, the pull of any anchors, the push of
+    /// any obstacles it is inside and, if enabled, the edge repulsion between its edges and the
+    /// edges they share no node with. This only reads the simulation, so forces can be inspected, or computed in parallel, separately from
+    /// moving the nodes with [ForceSimulation::integrate](ForceSimulation::integrate).
+    pub fn compute_forces(&self) -> Vec<Vector2D<f64>> {
+        let mut forces = vec![];
+        self.compute_forces_into(&mut vec![], &mut forces);
+        forces
+    }
+
+    /// Same as [ForceSimulation::compute_forces](ForceSimulation::compute_forces), but writes the
+    /// net forces into `forces` and uses `pairwise` as the matrix of pairwise forces, reusing
+    /// both buffers' memory.
+    fn compute_forces_into(
+        &self,
+        pairwise: &mut Vec<Vec<Vector2D<f64>>>,
+        forces: &mut Vec<Vector2D<f64>>,
+    ) {
+        let n_nodes = self.n_nodes();
+        self.calculate_forces_into(pairwise);
+        // Each net force is summed sequentially by source node index, which is already the order
+        // deterministic mode requires
+        forces.clear();
+        forces.extend((0..n_nodes).map(|j| {
+            let external = self.external_acceleration(j) * self.nodes[j].mass;
+            pairwise[..n_nodes]
+                .iter()
+                .fold(external, |total, row| total + row[j])
+        }));
+        self.add_edge_repulsion(forces);
+    }
+
+    /// Adds the push between every pair of edges that share no node to the net `forces`, if
+    /// edge repulsion is enabled.
+    ///
+    /// ### See also
+    /// [EdgeRepulsion](EdgeRepulsion)
+    fn add_edge_repulsion(&self, forces: &mut [Vector2D<f64>]) {
+        let Some(EdgeRepulsion { strength, range }) = self.edge_repulsion else {
+            return;
+        };
+        let endpoints = |edge: &Edge| {
+            (
+                self.nodes[edge.node1_idx].position,
+                self.nodes[edge.node2_idx].position,
+            )
+        };
+        for (i, e1) in self.edges.iter().enumerate() {
+            for e2 in self.edges.iter().skip(i + 1) {
+                if e1.has_node(e2.node1_idx) || e1.has_node(e2.node2_idx) {
+                    continue;
+                }
+                let (a1, a2) = endpoints(e1);
+                let (b1, b2) = endpoints(e2);
+                let (s, t) = Vector2D::closest_points_on_segments(&a1, &a2, &b1, &b2);
+                let mut offset = a1.lerp(&a2, s) - b1.lerp(&b2, t);
+                let gap = offset.magnitude();
+                if gap >= range {
+                    continue;
+                }
+                if gap == 0.0 {
+                    // Crossing edges have no gap to widen, so they are pushed apart along the
+                    // line between their midpoints
+                    offset = (a1 + a2) - (b1 + b2);
+                }
+                let push = offset.normalize() * (strength * (range - gap));
+                forces[e1.node1_idx] += push * (1.0 - s);
+                forces[e1.node2_idx] += push * s;
+                forces[e2.node1_idx] -= push * (1.0 - t);
+                forces[e2.node2_idx] -= push * t;
+            }
+        }
+    }
+
+    /// Calculates all pairwise forces between nodes. Pairs of nodes without an edge between them
+    /// are treated as connected by an edge of weight 0.
+    fn calculate_forces(&self) -> Vec<Vec<Vector2D<f64>>> {
+        let mut total_forces = vec![];
+        self.calculate_forces_into(&mut total_forces);
+        total_forces
+    }
+
+    /// Fills the first `n_nodes` entries of the first `n_nodes` rows of `total_forces` with the
+    /// pairwise forces, growing it if needed but never shrinking it, so a buffer reused across
+    /// steps keeps its memory.
+    fn calculate_forces_into(&self, total_forces: &mut Vec<Vec<Vector2D<f64>>>) {
+        // The matrix holds the total force that each node exerts on each other node. It is
+        // anti-symmetrical, so the force that node i exerts on node j is the negative of the force
+        // that node j exerts on node i.
+        let n_nodes = self.n_nodes();
+        if total_forces.len() < n_nodes {
+            total_forces.resize_with(n_nodes, Vec::new);
+        }
+        for row in total_forces[..n_nodes].iter_mut() {
+            row.clear();
+            row.resize(n_nodes, Vector2D::from_xy(0.0, 0.0));
+        }
+
+        // Loop over all pairs i, j of nodes
+        for (i, j) in self.node_pairs() {
+            let edge = self.get_edge_connecting_nodes(i, j);
+
+            // Distant pairs that are not connected exert no force on each other at all
+            if edge.is_none()
+                && self.is_beyond_repulsion_cutoff(
+                    self.distance_metric
+                        .distance(&self.nodes[i].position, &self.nodes[j].position),
+                )
+            {
+                continue;
+            }
+            let weight = edge.map_or(0.0, |edge| edge.weight);
+
+            // Calculate the total force that node i exerts on node j
+            total_forces[i][j] = self.scaled_force_n1_exerts_on_n2(
+                &self.nodes[i],
+                &self.nodes[j],
+                weight,
+                self.repulsion_scale(i) * self.repulsion_scale(j),
+            );
+
+            // Force is anti-symmetrical, so the force that node j exerts on node i is the
+            // negative of the force that node i exerts on node j
+            total_forces[j][i] = -total_forces[i][j];
+        }
+    }
+
+    /// Advances every node by one time step under the net `forces` on it, one per node, as
+    /// returned by [ForceSimulation::compute_forces](ForceSimulation::compute_forces). Damping,
+    /// the temperature, the centroid lock and the bounds all apply, so computing the forces and
+    /// then integrating them is equivalent to [ForceSimulation::step](ForceSimulation::step),
+    /// except that [ForceSimulation::last_forces](ForceSimulation::last_forces) is not updated.
+    ///
+    /// ### Panics
+    /// Panics if there is not exactly one force per node.
+    pub fn integrate(&mut self, forces: &[Vector2D<f64>]) {
+        assert_eq!(forces.len(), self.n_nodes(), "expected one force per node");
+        let center_of_mass = self.center_of_mass();
+        let mut previous_positions = std::mem::take(&mut self.scratch.previous_positions);
+        previous_positions.clear();
+        previous_positions.extend(self.nodes.iter().map(|node| node.position));
+
+        match self.integrator {
+            Integrator::Euler => self.integrate_euler(forces),
+            Integrator::VelocityVerlet => self.integrate_velocity_verlet(forces),
+        }
+
+        // Fail soft if a degenerate force produced inf or NaN: the affected node stays where it
+        // was and stops, rather than propagating NaN to every other node on the next step
+        for (node, &previous) in self.nodes.iter_mut().zip(previous_positions.iter()) {
+            if !node.position.is_finite() || !node.velocity.is_finite() {
+                node.position = node.position.sanitize(previous);
+                node.velocity = node.velocity.sanitize(Vector2D::from_xy(0.0, 0.0));
+            }
+        }
+        self.scratch.previous_positions = previous_positions;
+
+        if self.lock_centroid {
+            // Fixed nodes cannot be moved back, so the movable ones carry the whole correction,
+            // scaled up by the share of the total mass they hold
+            let total_mass: f64 = self.nodes.iter().map(|node| node.mass).sum();
+            let movable_mass: f64 = self
+                .nodes
+                .iter()
+                .filter(|node| !node.fixed)
+                .map(|node| node.mass)
+                .sum();
+            if movable_mass != 0.0 {
+                let correction =
+                    (self.center_of_mass() - center_of_mass) * (total_mass / movable_mass);
+                for node in self.nodes.iter_mut().filter(|node| !node.fixed) {
+                    node.position -= correction;
+                }
+            }
+        }
+
+        if self.remove_angular_momentum {
+            self.cancel_rotation();
+        }
+
+        self.apply_bounds();
+        for node in self.nodes.iter_mut().filter(|node| !node.fixed) {
+            node.position = self.distance_metric.wrap(node.position);
+        }
+        self.update_sleep();
+        self.steps_taken += 1;
+    }
+
+    /// Caps the displacement of a node in a single step at the temperature, if one is set.
+    fn cap_displacement(&self, delta_p: Vector2D<f64>) -> Vector2D<f64> {
+        match self.temperature {
+            Some(temperature) if delta_p.magnitude() > temperature => {
+                delta_p.normalize() * temperature
+            }
+            _ => delta_p,
+        }
+    }
+
+    /// Advances every node by one explicit Euler step, using the forces at the start of the step.
+    #[allow(clippy::needless_range_loop)]
+    fn integrate_euler(&mut self, forces: &[Vector2D<f64>]) {
+        let delta_time = self.current_time_step(forces);
+
+        // Loop over all nodes, updating their positions and velocities
+        for i in 0..self.n_nodes() {
+            if self.is_held(i) {
+                continue;
+            }
+            let acceleration = forces[i] / self.nodes[i].mass;
+            let delta_p_i =
+                self.nodes[i].velocity * delta_time + acceleration * delta_time.powi(2) / 2.0;
+            let delta_v_i = acceleration * delta_time;
+
+            let delta_p_i = self.cap_displacement(delta_p_i);
+            self.nodes[i].position += delta_p_i;
+            self.nodes[i].velocity = (self.nodes[i].velocity + delta_v_i) * (1.0 - self.damping);
+        }
+    }
+
+    /// Advances every node by one velocity-Verlet step: positions move using the forces at the
+    /// start of the step, then velocities are updated with the average of the accelerations
+    /// before and after the move.
+    #[allow(clippy::needless_range_loop)]
+    fn integrate_velocity_verlet(&mut self, forces: &[Vector2D<f64>]) {
+        let delta_time = self.current_time_step(forces);
+        let mut previous = std::mem::take(&mut self.scratch.accelerations);
+        previous.clear();
+        previous.extend(
+            forces
+                .iter()
+                .zip(self.nodes.iter())
+                .map(|(force, node)| *force / node.mass),
+        );
+
+        for i in 0..self.n_nodes() {
+            if self.is_held(i) {
+                continue;
+            }
+            let delta_p_i =
+                self.nodes[i].velocity * delta_time + previous[i] * delta_time.powi(2) / 2.0;
+            let delta_p_i = self.cap_displacement(delta_p_i);
+            self.nodes[i].position += delta_p_i;
+        }
+
+        let mut current = std::mem::take(&mut self.scratch.verlet_forces);
+        let mut pairwise = std::mem::take(&mut self.scratch.pairwise);
+        self.compute_forces_into(&mut pairwise, &mut current);
+        self.scratch.pairwise = pairwise;
+        for i in 0..self.n_nodes() {
+            if self.is_held(i) {
+                continue;
+            }
+            let node = &mut self.nodes[i];
+            let delta_v_i = (previous[i] + current[i] / node.mass) * delta_time / 2.0;
+            node.velocity = (node.velocity + delta_v_i) * (1.0 - self.damping);
+        }
+        self.scratch.accelerations = previous;
+        self.scratch.verlet_forces = current;
+        self.scratch.verlet_positions.clear();
+        self.scratch
+            .verlet_positions
+            .extend(self.nodes.iter().map(|node| node.position));
+    }
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use crate::assert_vec_approx;
+    use crate::math::vector_2d::approx_equal;
+    use std::cmp::Ordering;
+    use std::f64::consts::PI;
+
+    fn setup() -> (Vec<Node>, Vec<Edge>) {
+        let node1 = Node::new()
+            .id(1)
+            .label("Node 1")
+            .position(Vector2D::from_xy(0.0, 0.0))
+            .build();
+        let node2 = Node::new()
+            .id(2)
+            .label("Node 2")
+            .position(Vector2D::from_xy(1.0, 0.0))
+            .build();
+        let node3 = Node::new()
+            .id(3)
+            .label("Node 3")
+            .position(Vector2D::from_xy(0.0, 1.0))
+            .build();
+        let nodes: Vec<Node> = vec![node1, node2, node3];
+
+        let edge1 = Edge::new(0, 1, 1.0);
+        let edge2 = Edge::new(0, 2, 2.0);
+        let edge3 = Edge::new(1, 2, 3.0);
+        let edges: Vec<Edge> = vec![edge1, edge2, edge3];
+
+        (nodes, edges)
+    }
+
+    fn get_force_simulation() -> ForceSimulation {
+        let (nodes, edges) = setup();
+        ForceSimulation::new(nodes, edges, 1.0, 1.0, 1.0)
+    }
+
+    #[test]
+    pub fn test_state_hash() {
+        let (nodes, mut edges) = setup();
+        let fs = ForceSimulation::new(nodes.clone(), edges.clone(), 1.0, 1.0, 1.0);
+        let same = ForceSimulation::new(nodes.clone(), edges.clone(), 1.0, 1.0, 1.0);
+        assert_eq!(fs.state_hash(), same.state_hash());
+        assert_eq!(fs.state_hash(), fs.clone().state_hash());
+
+        edges[1].weight = 2.5;
+        let reweighted = ForceSimulation::new(nodes, edges, 1.0, 1.0, 1.0);
+        assert_ne!(fs.state_hash(), reweighted.state_hash());
+
+        let mut stepped = fs.clone();
+        stepped.step();
+        assert_ne!(fs.state_hash(), stepped.state_hash());
+    }
+
+    #[test]
+    pub fn test_layout_fingerprint() {
+        let (nodes, edges) = fully_connected(5);
+        let params = SimParams {
+            time_step: 0.05,
+            damping: 0.1,
+            gravity: 1.0,
+            ..SimParams::default()
+        };
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut fs =
+            ForceSimulation::new_with_random_positions(nodes, edges, params, 5.0, &mut rng);
+        fs.steps()
+            .take(10_000)
+            .find(|report| report.max_displacement < 1e-9);
+        assert!(fs.is_settled(1e-9));
+
+        let fingerprint = fs.layout_fingerprint();
+        assert_eq!(fingerprint.len(), 16);
+        assert_eq!(fingerprint, "c070ee075f39e95b");
+
+        // the fingerprint follows the ids, not the order of the nodes
+        let mut reordered = fs.clone();
+        reordered.nodes.reverse();
+        assert_eq!(reordered.layout_fingerprint(), fingerprint);
+
+        // movement below the rounding is ignored, and movement above it is not
+        let mut nudged = fs.clone();
+        nudged.nodes[0].position += Vector2D::from_xy(1e-9, 0.0);
+        assert_eq!(nudged.layout_fingerprint(), fingerprint);
+        nudged.nodes[0].position += Vector2D::from_xy(1e-3, 0.0);
+        assert_ne!(nudged.layout_fingerprint(), fingerprint);
+    }
+
+    #[test]
+    pub fn test_toroidal_metric() {
+        // two nodes near opposite edges of a 10 x 10 canvas, 9 apart in the plane but 1 apart
+        // across the wrap, with a light edge so attraction beats repulsion
+        let nodes = vec![
+            Node::at(0, Vector2D::from_xy(0.5, 5.0)),
+            Node::at(1, Vector2D::from_xy(9.5, 5.0)),
+        ];
+        let edges = vec![Edge::between(0, 1, 0.5)];
+        let flat = ForceSimulation::new(nodes.clone(), edges.clone(), 0.01, 1.0, 1.0);
+        let torus = DistanceMetric::Toroidal {
+            width: 10.0,
+            height: 10.0,
+        };
+        let params = SimParams {
+            time_step: 0.01,
+            distance_metric: torus,
+            ..SimParams::default()
+        };
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, params);
+
+        // in the plane node 1 is pulled left, but on the torus it is pulled right, across the edge
+        assert!(flat.compute_forces()[1].x < 0.0);
+        assert!(fs.compute_forces()[1].x > 0.0);
+        assert!(fs.compute_forces()[0].x < 0.0);
+
+        // the nodes close in across the wrap and stay on the canvas
+        fs.step_n(20);
+        let (a, b) = (fs.get_nodes()[0].position, fs.get_nodes()[1].position);
+        assert!(torus.distance(&a, &b) < 1.0);
+        for position in [a, b] {
+            assert!((0.0..10.0).contains(&position.x));
+            assert!((0.0..10.0).contains(&position.y));
+        }
+    }
+
+    #[test]
+    pub fn test_step_report_across_toroidal_seam() {
+        let mut node = Node::at(0, Vector2D::from_xy(9.9, 5.0));
+        node.velocity = Vector2D::from_xy(1.0, 0.0);
+        let params = SimParams {
+            time_step: 0.2,
+            distance_metric: DistanceMetric::toroidal(10.0, 10.0).unwrap(),
+            ..SimParams::default()
+        };
+        let mut fs = ForceSimulation::new_with_params(vec![node], vec![], params);
+
+        // the node wraps from x = 9.9 to x = 0.1, which is 0.2 across the seam, not 9.8
+        let report = fs.step();
+        assert!((fs.get_nodes()[0].position.x - 0.1).abs() < 1e-9);
+        assert!((report.max_displacement - 0.2).abs() < 1e-9);
+        assert!((report.total_movement - 0.2).abs() < 1e-9);
+    }
+
+    #[test]
+    pub fn test_get_node_mass() {
+        let mut force_simulation = get_force_simulation();
+
+        assert_eq!(force_simulation.get_node_mass(0), 1.0 + 2.0);
+        assert_eq!(force_simulation.get_node_mass(1), 1.0 + 3.0);
+        assert_eq!(force_simulation.get_node_mass(2), 2.0 + 3.0);
+    }
+
+    #[test]
+    pub fn test_negative_weight_repels() {
+        let pair = |weight: f64| {
+            let nodes = vec![
+                Node::at(0, Vector2D::from_xy(0.0, 0.0)),
+                Node::at(1, Vector2D::from_xy(1.0, 0.0)),
+            ];
+            ForceSimulation::new(nodes, vec![Edge::between(0, 1, weight)], 0.01, 1.0, 1.0)
+        };
+        let mut attracted = pair(2.0);
+        let mut repelled = pair(-2.0);
+
+        // the mass comes from the size of the weight, whatever its sign
+        for (a, r) in attracted.get_nodes().iter().zip(repelled.get_nodes()) {
+            assert_eq!(a.mass, 2.0);
+            assert_eq!(r.mass, 2.0);
+        }
+
+        // the negative edge pushes its nodes apart from the start
+        let force = repelled.compute_forces()[1];
+        assert!(force.x > 0.0);
+        assert!(approx_equal(force.y, 0.0, 1e-12));
+
+        attracted.step_n(50);
+        repelled.step_n(50);
+        let separation = |fs: &ForceSimulation| {
+            fs.get_nodes()[0]
+                .position
+                .distance(&fs.get_nodes()[1].position)
+        };
+        assert!(separation(&repelled) > 1.0);
+        assert!(separation(&repelled) > separation(&attracted));
+    }
+
+    #[test]
+    pub fn test_masses_set_on_new() {
+        let fs = get_force_simulation();
+
+        // each node's mass is the sum of its incident edge weights
+        let expected = [1.0 + 2.0, 1.0 + 3.0, 2.0 + 3.0];
+        for (i, node) in fs.get_nodes().iter().enumerate() {
+            assert_eq!(node.mass, expected[i]);
+            assert_eq!(fs.masses[i], expected[i]);
+        }
+    }
+
+    #[test]
+    pub fn test_masses_isolated_node() {
+        let (nodes, _) = setup();
+        let fs = ForceSimulation::new(nodes, vec![Edge::new(0, 1, 2.0)], 1.0, 1.0, 1.0);
+
+        assert_eq!(fs.get_nodes()[0].mass, 2.0);
+        assert_eq!(fs.get_nodes()[1].mass, 2.0);
+
+        // node 2 has no edges, so it keeps its default mass
+        assert_eq!(fs.get_nodes()[2].mass, 1.0);
+    }
+
+    #[test]
+    pub fn test_repulsive_force_n1_exerts_on_n2() {
+        let mut force_simulation = get_force_simulation();
+
+        let distance = 1.0;
+        let n1_mass = 1.0;
+        let n2_mass = 1.0;
+        let direction = 0.0;
 
         let expected = Vector2D::from_xy(1.0, 0.0);
         let actual =
@@ -374,125 +2776,2837 @@ pub mod test {
     }
 
     #[test]
-    pub fn test_attractive_force_n1_exerts_on_n2() {
-        let mut force_simulation = get_force_simulation();
+    pub fn test_attractive_force_n1_exerts_on_n2() {
+        let mut force_simulation = get_force_simulation();
+
+        let distance = 1.0;
+        let weight = 1.0;
+        let direction = 0.0;
+
+        let expected = Vector2D::from_xy(1.0, 0.0);
+        let actual = force_simulation.attractive_force_n1_exerts_on_n2(distance, weight, direction);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    pub fn test_total_force_n1_exerts_on_n2() {
+        let (nodes, edges) = setup();
+        let weight = edges[1].weight;
+        let mut fs = get_force_simulation();
+
+        let mut n1 = &nodes[0];
+        let mut n2 = &nodes[2];
+        let repulsive_force: Vector2D<f64> = fs
+            .repulsive_force_n1_exerts_on_n2(
+                n1.position.distance(&n2.position),
+                n1.mass,
+                n2.mass,
+                n1.position.relative_to(&n2.position).angle(),
+            )
+            .round(5);
+
+        println!("repulsive_force: {:?}", repulsive_force);
+
+        let attractive_force = fs
+            .attractive_force_n1_exerts_on_n2(
+                n1.position.distance(&n2.position),
+                weight,
+                n1.position.relative_to(&n2.position).angle(),
+            )
+            .round(5);
+
+        println!("attractive_force: {:?}", attractive_force);
+
+        let expected = attractive_force - repulsive_force;
+
+        println!("expected: {:?}", expected);
+
+        let actual = fs.total_force_n1_exerts_on_n2(n1, n2, weight).round(5);
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    pub fn test_acceleration_from_force_n1_exerts_on_n2() {
+        let (nodes, edges) = setup();
+        let weight = edges[1].weight;
+        let mut fs = get_force_simulation();
+
+        let mut n1 = &nodes[0];
+        let mut n2 = &nodes[2];
+        let force = fs.total_force_n1_exerts_on_n2(n1, n2, weight);
+
+        println!("net force: {:?}", force);
+
+        let expected = force / n2.mass;
+
+        println!("expected: {:?}", expected);
+
+        let actual = fs.acceleration_from_force_n1_exerts_on_n2(n1, n2, weight);
+        assert_eq!(actual.round(5), expected.round(5));
+    }
+
+    #[test]
+    pub fn test_chg_in_velocity_from_force_n1_exerts_on_n2() {
+        let time_step = 0.25;
+        let (nodes, edges) = setup();
+        let weight = edges[1].weight;
+        let mut fs = get_force_simulation();
+
+        let mut n1 = &nodes[0];
+        let mut n2 = &nodes[2];
+        let acceleration = fs.acceleration_from_force_n1_exerts_on_n2(n1, n2, weight);
+
+        println!("acceleration: {:?}", acceleration);
+
+        let v0 = n2.velocity;
+        let delta_v = acceleration * time_step;
+
+        println!("expected delta v: {:?}", delta_v);
+
+        let actual = fs.chg_in_velocity_from_force_n1_exerts_on_n2(n1, n2, weight, time_step);
+        assert_eq!(actual.round(5), delta_v.round(5));
+    }
+
+    #[test]
+    pub fn test_chg_in_position_from_force_n1_exerts_on_n2() {
+        let time_step: f64 = 5.0;
+        let (nodes, edges) = setup();
+        let weight = edges[1].weight;
+        let mut fs = get_force_simulation();
+
+        let mut n1 = &nodes[0];
+        let mut n2 = &nodes[2];
+        let acceleration = fs.acceleration_from_force_n1_exerts_on_n2(n1, n2, weight);
+
+        println!("acceleration: {:?}", acceleration.round(3));
+
+        let p0 = n2.position;
+        let v0 = n2.velocity;
+
+        println!("p0: {:?}", p0.round(3));
+        println!("v0: {:?}", v0.round(3));
+
+        // the v0 term is applied once per node, not per pair
+        let pf = p0 + acceleration * time_step.powi(2) / 2.0;
+
+        println!("expected pf: {:?}", pf.round(3));
+
+        let delta_p = pf - p0;
+
+        println!("expected delta p: {:?}", delta_p.round(3));
+
+        let actual = fs.chg_in_position_from_force_n1_exerts_on_n2(n1, n2, weight, time_step);
+        assert_eq!(actual.round(5), delta_p.round(5));
+    }
+
+    #[test]
+    pub fn test_get_node_by_id() {
+        // ids deliberately do not match the vector positions
+        let (mut nodes, edges) = setup();
+        nodes[0].id = 30;
+        nodes[1].id = 10;
+        nodes[2].id = 20;
+        let fs = ForceSimulation::new(nodes, edges, 1.0, 1.0, 1.0);
+
+        assert_eq!(fs.get_node_by_id(10).unwrap().label, "Node 2");
+        assert_eq!(fs.get_node_by_id(20).unwrap().label, "Node 3");
+        assert_eq!(fs.get_node_by_id(30).unwrap().label, "Node 1");
+        assert!(fs.get_node_by_id(0).is_none());
+        assert!(fs.get_node_by_id(1).is_none());
+
+        assert_eq!(fs.position_of(10), Some(Vector2D::from_xy(1.0, 0.0)));
+        assert_eq!(fs.position_of(20), Some(Vector2D::from_xy(0.0, 1.0)));
+        assert_eq!(fs.position_of(30), Some(Vector2D::from_xy(0.0, 0.0)));
+        assert_eq!(fs.position_of(2), None);
+    }
+
+    #[test]
+    pub fn test_node_index_of() {
+        // ids deliberately do not match the vector positions
+        let (mut nodes, _) = setup();
+        nodes[0].id = 30;
+        nodes[1].id = 10;
+        nodes[2].id = 20;
+        let mut fs = ForceSimulation::new(nodes, vec![], 1.0, 1.0, 1.0);
+
+        assert_eq!(fs.node_index_of(30), Some(0));
+        assert_eq!(fs.node_index_of(10), Some(1));
+        assert_eq!(fs.node_index_of(20), Some(2));
+        assert_eq!(fs.node_index_of(0), None);
+        for node_idx in 0..3 {
+            let id = fs.node_id_at(node_idx).unwrap();
+            assert_eq!(fs.node_index_of(id), Some(node_idx));
+        }
+        assert_eq!(fs.node_id_at(3), None);
+
+        // edges added by id refer to the matching indices
+        fs.add_edge_by_id(10, 20, 2.0).unwrap();
+        fs.add_edge_by_id(30, 20, 1.0).unwrap();
+        let edges = fs.get_edges();
+        assert_eq!((edges[0].node1_idx, edges[0].node2_idx), (1, 2));
+        assert_eq!((edges[1].node1_idx, edges[1].node2_idx), (0, 2));
+        assert_eq!(fs.edge_between(2, 1).unwrap().weight, 2.0);
+        assert_eq!(fs.get_nodes()[2].mass, 3.0);
+
+        // an unknown id adds nothing
+        assert!(matches!(
+            fs.add_edge_by_id(10, 1, 1.0),
+            Err(Error::UnknownNodeId(1))
+        ));
+        assert_eq!(fs.n_edges(), 2);
+
+        // the map follows the ids when they are shuffled
+        fs.shuffle_ids(5);
+        for node_idx in 0..3 {
+            let id = fs.node_id_at(node_idx).unwrap();
+            assert_eq!(fs.node_index_of(id), Some(node_idx));
+        }
+    }
+
+    #[test]
+    pub fn test_connect() {
+        let nodes: Vec<Node> = [7, 3, 5]
+            .iter()
+            .map(|&id| Node::new().id(id).build())
+            .collect();
+        let mut fs = ForceSimulation::new(nodes, vec![], 0.01, 1.0, 1.0);
+
+        fs.connect(5, 7, 1.5).unwrap();
+        assert_eq!(fs.get_edges(), &vec![Edge::new(2, 0, 1.5)]);
+        assert_eq!(fs.edge_between(0, 2).unwrap().weight, 1.5);
+
+        assert!(matches!(
+            fs.connect(3, 4, 1.0),
+            Err(Error::UnknownNodeId(4))
+        ));
+        assert!(matches!(
+            fs.connect(0, 3, 1.0),
+            Err(Error::UnknownNodeId(0))
+        ));
+        assert_eq!(fs.n_edges(), 1);
+    }
+
+    #[test]
+    pub fn test_add_jitter() {
+        let magnitude = 0.1;
+        let mut fs1 = get_force_simulation();
+        let mut fs2 = get_force_simulation();
+        let original: Vec<Vector2D<f64>> = fs1.get_nodes().iter().map(|n| n.position).collect();
+
+        fs1.add_jitter(magnitude, 42);
+        fs2.add_jitter(magnitude, 42);
+
+        for (i, node) in fs1.get_nodes().iter().enumerate() {
+            let offset = node.position - original[i];
+            assert!(offset.x.abs() <= magnitude);
+            assert!(offset.y.abs() <= magnitude);
+            assert_ne!(node.position, original[i]);
+
+            // same seed, same jitter
+            assert_eq!(node.position, fs2.get_nodes()[i].position);
+        }
+
+        // a different seed gives a different jitter
+        let mut fs3 = get_force_simulation();
+        fs3.add_jitter(magnitude, 7);
+        assert_ne!(fs3.get_nodes()[0].position, fs1.get_nodes()[0].position);
+    }
+
+    #[test]
+    pub fn test_add_jitter_from_state() {
+        let magnitude = 0.1;
+        let seeded = |seed: u64| {
+            let (nodes, edges) = setup();
+            let params = SimParams {
+                seed,
+                ..SimParams::default()
+            };
+            ForceSimulation::new_with_params(nodes, edges, params)
+        };
+        let mut fs1 = seeded(42);
+        let mut fs2 = seeded(42);
+        let original: Vec<Vector2D<f64>> = fs1.get_nodes().iter().map(|n| n.position).collect();
+
+        fs1.add_jitter_from_state(magnitude);
+        fs2.add_jitter_from_state(magnitude);
+
+        for (i, node) in fs1.get_nodes().iter().enumerate() {
+            let offset = node.position - original[i];
+            assert!(offset.x.abs() <= magnitude);
+            assert!(offset.y.abs() <= magnitude);
+            assert_ne!(node.position, original[i]);
+
+            // same seed, same jitter
+            assert_eq!(node.position, fs2.get_nodes()[i].position);
+        }
+
+        // a different seed gives a different jitter
+        let mut fs3 = seeded(7);
+        fs3.add_jitter_from_state(magnitude);
+        assert_ne!(fs3.get_nodes()[0].position, fs1.get_nodes()[0].position);
+
+        // repeated calls do not repeat the same offsets
+        let before = fs1.get_nodes()[0].position;
+        fs1.add_jitter_from_state(magnitude);
+        assert_ne!(fs1.get_nodes()[0].position - before, before - original[0]);
+    }
+
+    #[test]
+    pub fn test_seed_survives_serialization() {
+        let (nodes, edges) = setup();
+        let params = SimParams {
+            seed: 1234,
+            ..SimParams::default()
+        };
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, params);
+        fs.add_jitter_from_state(0.5);
+        fs.step();
+
+        let json = fs.to_json().unwrap();
+        let mut restored = ForceSimulation::from_json(&json).unwrap();
+        assert_eq!(restored.seed(), 1234);
+        assert_eq!(restored.edge_index, fs.edge_index);
+
+        // the restored simulation continues the same jitter sequence
+        fs.add_jitter_from_state(0.5);
+        restored.add_jitter_from_state(0.5);
+        for (node, restored_node) in fs.get_nodes().iter().zip(restored.get_nodes()) {
+            assert_eq!(node.position, restored_node.position);
+        }
+    }
+
+    #[test]
+    pub fn test_edge_length_stats() {
+        let fs = get_force_simulation();
+        let sqrt_2 = 2.0_f64.sqrt();
+
+        // edges 0-1 and 0-2 have length 1, and edge 1-2 has length sqrt(2)
+        let (min, mean, max) = fs.edge_length_stats();
+        let expected_mean = (2.0 + sqrt_2) / 3.0;
+        assert_eq!(min, 1.0);
+        assert!(approx_equal(max, sqrt_2, 1e-10));
+        assert!(approx_equal(mean, expected_mean, 1e-10));
+
+        let expected_variance =
+            (2.0 * (1.0 - expected_mean).powi(2) + (sqrt_2 - expected_mean).powi(2)) / 3.0;
+        assert!(approx_equal(
+            fs.edge_length_variance(),
+            expected_variance,
+            1e-10
+        ));
+    }
+
+    #[test]
+    pub fn test_edge_length_stats_no_edges() {
+        let (nodes, _) = setup();
+        let fs = ForceSimulation::new(nodes, vec![], 1.0, 1.0, 1.0);
+        assert_eq!(fs.edge_length_stats(), (0.0, 0.0, 0.0));
+        assert_eq!(fs.edge_length_variance(), 0.0);
+    }
+
+    fn fully_connected(n_nodes: usize) -> (Vec<Node>, Vec<Edge>) {
+        let nodes: Vec<Node> = (0..n_nodes)
+            .map(|i| {
+                Node::new()
+                    .id(i)
+                    .position(Vector2D::from_rtheta(10.0, i as f64))
+                    .build()
+            })
+            .collect();
+        let mut edges: Vec<Edge> = Vec::new();
+        for i in 0..n_nodes {
+            for j in (i + 1)..n_nodes {
+                edges.push(Edge::new(i, j, 1.0 + (i + j) as f64 / 100.0));
+            }
+        }
+        (nodes, edges)
+    }
+
+    #[test]
+    pub fn test_clean_edges() {
+        let (nodes, _) = setup();
+        let edges = vec![
+            Edge::new(0, 1, 1.0),
+            Edge::new(2, 2, 5.0),
+            Edge::new(1, 2, 3.0),
+            Edge::new(1, 0, 2.0),
+        ];
+
+        // the self-loop counts toward the mass of node 2 until it is removed
+        let mut fs = ForceSimulation::new(nodes.clone(), edges.clone(), 0.01, 1.0, 1.0);
+        assert_eq!(fs.get_nodes()[2].mass, 8.0);
+
+        assert_eq!(fs.clean_edges(), 2);
+        assert_eq!(
+            fs.get_edges(),
+            &vec![Edge::new(0, 1, 3.0), Edge::new(1, 2, 3.0)]
+        );
+        let masses: Vec<f64> = fs.get_nodes().iter().map(|node| node.mass).collect();
+        assert_eq!(masses, vec![3.0, 6.0, 3.0]);
+        assert_eq!(fs.get_edge_connecting_nodes(1, 0).unwrap().weight, 3.0);
+        assert_eq!(fs.clean_edges(), 0);
+
+        // or the edges can be cleaned on construction
+        let params = SimParams {
+            clean_edges: true,
+            ..SimParams::default()
+        };
+        let cleaned = ForceSimulation::new_with_params(nodes, edges, params);
+        assert_eq!(cleaned.get_edges(), fs.get_edges());
+        assert_eq!(cleaned.get_nodes()[2].mass, 3.0);
+    }
+
+    #[test]
+    pub fn test_dedup_parallel() {
+        let (nodes, _) = setup();
+        let edges = vec![
+            Edge::new(0, 1, 1.0),
+            Edge::new(1, 2, 3.0),
+            Edge::new(1, 0, 2.0),
+        ];
+        let mut summed = ForceSimulation::new(nodes.clone(), edges.clone(), 0.01, 1.0, 1.0);
+        let mut largest = summed.clone();
+
+        assert_eq!(summed.dedup_parallel(EdgeMerge::Sum), 1);
+        assert_eq!(
+            summed.get_edges(),
+            &vec![Edge::new(0, 1, 3.0), Edge::new(1, 2, 3.0)]
+        );
+        assert_eq!(summed.get_nodes()[0].mass, 3.0);
+        assert_eq!(summed.dedup_parallel(EdgeMerge::Sum), 0);
+
+        assert_eq!(largest.dedup_parallel(EdgeMerge::Max), 1);
+        assert_eq!(
+            largest.get_edges(),
+            &vec![Edge::new(0, 1, 2.0), Edge::new(1, 2, 3.0)]
+        );
+        assert_eq!(largest.get_nodes()[0].mass, 2.0);
+        assert_eq!(largest.edge_between(1, 0).unwrap().weight, 2.0);
+    }
+
+    #[test]
+    pub fn test_remove_edges_between() {
+        let (nodes, _) = setup();
+        let edges = vec![
+            Edge::new(0, 1, 1.0),
+            Edge::new(1, 2, 3.0),
+            Edge::new(1, 0, 2.0),
+        ];
+        let mut fs = ForceSimulation::new(nodes, edges, 0.01, 1.0, 1.0);
+        assert_eq!(fs.remove_edges_between(1, 0), 2);
+        assert_eq!(fs.get_edges(), &vec![Edge::new(1, 2, 3.0)]);
+        assert!(fs.edge_between(0, 1).is_err());
+        assert_eq!(fs.get_nodes()[1].mass, 3.0);
+        assert_eq!(fs.remove_edges_between(0, 1), 0);
+    }
+
+    #[test]
+    pub fn test_edges_of_and_degree() {
+        let n_nodes = 6;
+        let (nodes, edges) = fully_connected(n_nodes);
+        let fs = ForceSimulation::new_with_params(nodes, edges, SimParams::default());
+        for node_idx in 0..n_nodes {
+            let incident = fs.edges_of(node_idx);
+            assert_eq!(incident.len(), n_nodes - 1);
+            assert!(incident.iter().all(|edge| edge.has_node(node_idx)));
+            assert_eq!(fs.degree(node_idx), n_nodes - 1);
+        }
+        assert!(fs.edges_of(n_nodes).is_empty());
+        assert_eq!(fs.degree(n_nodes), 0);
+    }
+
+    #[test]
+    pub fn test_degree_histogram() {
+        // a star: one hub with five leaves
+        let nodes: Vec<Node> = (0..6).map(|i| Node::new().id(i).build()).collect();
+        let edges = (1..6).map(|leaf| Edge::between(0, leaf, 1.0)).collect();
+        let fs = ForceSimulation::new(nodes, edges, 0.01, 1.0, 1.0);
+        assert_eq!(fs.degree_histogram(), vec![0, 5, 0, 0, 0, 1]);
+        // five nodes of degree 1 and one of degree 5 lie on the line of slope -1
+        assert!(approx_equal(
+            fs.estimate_power_law_exponent().unwrap(),
+            1.0,
+            1e-12
+        ));
+
+        // without edges every node has degree 0, which leaves nothing to fit
+        let (nodes, _) = setup();
+        let fs = ForceSimulation::new(nodes, vec![], 0.01, 1.0, 1.0);
+        assert_eq!(fs.degree_histogram(), vec![3]);
+        assert_eq!(fs.estimate_power_law_exponent(), None);
+
+        let empty = ForceSimulation::new(vec![], vec![], 0.01, 1.0, 1.0);
+        assert!(empty.degree_histogram().is_empty());
+    }
+
+    #[test]
+    pub fn test_to_csr() {
+        let (nodes, edges) = fully_connected(5);
+        let fs = ForceSimulation::new(nodes, edges, 0.01, 1.0, 1.0);
+        let csr = fs.to_csr();
+
+        // the rows match the neighbors found by scanning the edge list
+        for i in 0..fs.n_nodes() {
+            let mut neighbors: Vec<usize> = fs
+                .get_edges()
+                .iter()
+                .filter_map(|edge| match (edge.node1_idx, edge.node2_idx) {
+                    (a, b) if a == i => Some(b),
+                    (a, b) if b == i => Some(a),
+                    _ => None,
+                })
+                .collect();
+            neighbors.sort_unstable();
+            assert_eq!(csr.neighbors(i), neighbors.as_slice());
+            for (&j, &weight) in csr.neighbors(i).iter().zip(csr.neighbor_weights(i)) {
+                assert_eq!(fs.get_edge_connecting_nodes(i, j).unwrap().weight, weight);
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_get_edge_connecting_nodes() {
+        let fs = get_force_simulation();
+        assert_eq!(fs.get_edge_connecting_nodes(0, 1).unwrap().weight, 1.0);
+        assert_eq!(fs.get_edge_connecting_nodes(1, 0).unwrap().weight, 1.0);
+        assert_eq!(fs.get_edge_connecting_nodes(2, 0).unwrap().weight, 2.0);
+        assert_eq!(fs.get_edge_connecting_nodes(1, 2).unwrap().weight, 3.0);
+        assert!(fs.get_edge_connecting_nodes(0, 0).is_none());
+        assert!(fs.get_edge_connecting_nodes(0, 3).is_none());
+    }
+
+    #[test]
+    pub fn test_edge_index_matches_linear_scan() {
+        let (nodes, edges) = fully_connected(100);
+        let mut fs = ForceSimulation::new(nodes, edges.clone(), 0.01, 1.0, 1.0);
+
+        let linear_scan = |a: usize, b: usize| {
+            edges
+                .iter()
+                .find(|&edge| edge.has_node(a) && edge.has_node(b))
+                .cloned()
+        };
+
+        for i in 0..100 {
+            for j in (i + 1)..100 {
+                assert_eq!(
+                    fs.get_edge_connecting_nodes(i, j).cloned(),
+                    linear_scan(i, j)
+                );
+            }
+        }
+
+        // the net forces with every weight found by the linear scan, summed in the same order
+        let pair_force = |i: usize, j: usize| {
+            let weight = linear_scan(i, j).map_or(0.0, |edge| edge.weight);
+            let scale = fs.repulsion_scale(i) * fs.repulsion_scale(j);
+            fs.scaled_force_n1_exerts_on_n2(&fs.nodes[i], &fs.nodes[j], weight, scale)
+        };
+        let linear_forces: Vec<Vector2D<f64>> = (0..100)
+            .map(|j| {
+                let external = fs.external_acceleration(j) * fs.nodes[j].mass;
+                (0..100).fold(external, |total, i| match i.cmp(&j) {
+                    Ordering::Less => total + pair_force(i, j),
+                    Ordering::Greater => total - pair_force(j, i),
+                    Ordering::Equal => total,
+                })
+            })
+            .collect();
+
+        // a step on the indexed lookup lands exactly where the linear-scan forces do
+        let mut linear = fs.clone();
+        linear.integrate(&linear_forces);
+        fs.step();
+        assert_eq!(fs.export_positions(), linear.export_positions());
+    }
+
+    #[test]
+    pub fn test_new_with_params() {
+        let (nodes, edges) = setup();
+        let params = SimParams {
+            repulsion_constant: 3.0,
+            ..SimParams::default()
+        };
+        let fs = ForceSimulation::new_with_params(nodes, edges, params);
+        assert_eq!(fs.repulsion_constant, 3.0);
+        assert_eq!(fs.time_step, 1.0);
+        assert_eq!(fs.attraction_constant, 1.0);
+        assert_eq!(fs.damping, 0.0);
+        assert_eq!(fs.gravity, 0.0);
+        assert_eq!(fs.temperature, None);
+
+        // new delegates to new_with_params with the remaining fields defaulted
+        let (nodes, edges) = setup();
+        let fs = ForceSimulation::new(nodes, edges, 0.5, 2.0, 3.0);
+        assert_eq!(fs.time_step, 0.5);
+        assert_eq!(fs.repulsion_constant, 2.0);
+        assert_eq!(fs.attraction_constant, 3.0);
+        assert_eq!(fs.damping, 0.0);
+    }
+
+    #[test]
+    pub fn test_temperature_caps_displacement() {
+        let (nodes, edges) = setup();
+        let params = SimParams {
+            temperature: Some(0.01),
+            ..SimParams::default()
+        };
+        let mut fs = ForceSimulation::new_with_params(nodes.clone(), edges, params);
+        fs.step();
+        for (i, node) in fs.get_nodes().iter().enumerate() {
+            assert!(node.position.distance(&nodes[i].position) <= 0.01 + 1e-12);
+        }
+    }
+
+    #[test]
+    pub fn test_damping_and_gravity() {
+        // a single isolated node feels no pairwise forces, only gravity
+        let node = Node::new()
+            .position(Vector2D::from_xy(2.0, 0.0))
+            .velocity(Vector2D::from_xy(0.0, 1.0))
+            .build();
+        let params = SimParams {
+            damping: 0.5,
+            gravity: 1.0,
+            ..SimParams::default()
+        };
+        let mut fs = ForceSimulation::new_with_params(vec![node], vec![], params);
+        fs.step();
+
+        // gravity acceleration is -(2, 0), so position moves by v * dt + a * dt^2 / 2 = (-1, 1),
+        // and the velocity (0, 1) + a * dt = (-2, 1) is then halved by damping
+        let node = &fs.get_nodes()[0];
+        assert_eq!(node.position, Vector2D::from_xy(1.0, 1.0));
+        assert_eq!(node.velocity, Vector2D::from_xy(-1.0, 0.5));
+    }
+
+    #[test]
+    pub fn test_gravity_center() {
+        let (nodes, edges) = setup();
+        let params = SimParams {
+            time_step: 0.05,
+            damping: 0.1,
+            gravity: 1.0,
+            ..SimParams::default()
+        };
+        let center = Vector2D::from_xy(10.0, -5.0);
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, params);
+        fs.set_gravity_center(center);
+        let initial_distance = fs.center_of_mass().distance(&center);
+        fs.step_n(500);
+
+        // the layout has gathered around the gravity center rather than the origin
+        assert!(fs.center_of_mass().distance(&center) < 0.01 * initial_distance);
+        for node in fs.get_nodes() {
+            assert!(node.position.distance(&center) < node.position.magnitude());
+        }
+    }
+
+    #[test]
+    pub fn test_step_n() {
+        let mut fs1 = get_force_simulation();
+        let mut fs2 = get_force_simulation();
+        fs1.time_step = 0.01;
+        fs2.time_step = 0.01;
+
+        fs1.step_n(5);
+        for _ in 0..5 {
+            fs2.step();
+        }
+
+        for (n1, n2) in fs1.get_nodes().iter().zip(fs2.get_nodes()) {
+            assert_eq!(n1.position, n2.position);
+            assert_eq!(n1.velocity, n2.velocity);
+        }
+        assert_ne!(fs1.get_nodes()[0].position, setup().0[0].position);
+    }
+
+    #[test]
+    pub fn test_system_energy() {
+        let fs = get_force_simulation();
+        let sqrt_2 = 2.0_f64.sqrt();
+
+        // masses are 3, 4 and 5; pairs 0-1 and 0-2 are at distance 1, and pair 1-2 at sqrt(2).
+        // Repulsion contributes m1 * m2 / d and the linear springs weight * d^2 / 2.
+        let expected = (3.0 * 4.0 + 1.0 / 2.0)
+            + (3.0 * 5.0 + 2.0 / 2.0)
+            + (4.0 * 5.0 / sqrt_2 + 3.0 * 2.0 / 2.0);
+        assert!(approx_equal(fs.system_energy(), expected, 1e-10));
+    }
+
+    #[test]
+    pub fn test_system_energy_decreases_with_damping() {
+        let (nodes, edges) = setup();
+        let params = SimParams {
+            time_step: 0.01,
+            damping: 0.5,
+            ..SimParams::default()
+        };
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, params);
+        let initial_energy = fs.system_energy();
+        for _ in 0..200 {
+            fs.step();
+        }
+        assert!(fs.system_energy() < initial_energy);
+    }
+
+    #[test]
+    pub fn test_last_forces() {
+        // equilateral triangle with equal weights, so every node has the same mass
+        let nodes: Vec<Node> = (0..3)
+            .map(|i| {
+                Node::new()
+                    .id(i)
+                    .position(Vector2D::from_rtheta(1.0, 2.0 * PI * i as f64 / 3.0))
+                    .build()
+            })
+            .collect();
+        let edges = vec![
+            Edge::new(0, 1, 1.0),
+            Edge::new(0, 2, 1.0),
+            Edge::new(1, 2, 1.0),
+        ];
+        let mut fs = ForceSimulation::new(nodes, edges, 0.01, 1.0, 1.0);
+
+        assert_eq!(fs.last_forces().len(), 3);
+        assert!(fs.last_forces().iter().all(|f| f.magnitude() == 0.0));
+
+        fs.step();
+        let magnitudes: Vec<f64> = fs.last_forces().iter().map(|f| f.magnitude()).collect();
+        assert!(magnitudes[0] > 0.0);
+        assert!(approx_equal(magnitudes[0], magnitudes[1], 1e-10));
+        assert!(approx_equal(magnitudes[0], magnitudes[2], 1e-10));
+    }
+
+    #[test]
+    pub fn test_set_bounds() {
+        let mut fs = get_force_simulation();
+        fs.time_step = 0.1;
+        fs.set_bounds(Vector2D::from_xy(-0.5, 1.5), Vector2D::from_xy(1.5, -0.5));
+
+        for _ in 0..100 {
+            fs.step();
+            for node in fs.get_nodes() {
+                assert!(node.position.x >= -0.5 && node.position.x <= 1.5);
+                assert!(node.position.y >= -0.5 && node.position.y <= 1.5);
+            }
+        }
+
+        // without bounds the same simulation leaves the box
+        let mut fs = get_force_simulation();
+        fs.time_step = 0.1;
+        fs.step_n(100);
+        assert!(fs
+            .get_nodes()
+            .iter()
+            .any(|node| node.position.magnitude() > 2.5));
+    }
+
+    #[test]
+    pub fn test_center_of_mass() {
+        let fs = get_force_simulation();
+
+        // masses are 3, 4 and 5 at (0, 0), (1, 0) and (0, 1)
+        let com = fs.center_of_mass();
+        assert!(approx_equal(com.x, 4.0 / 12.0, 1e-10));
+        assert!(approx_equal(com.y, 5.0 / 12.0, 1e-10));
+    }
+
+    #[test]
+    pub fn test_lock_centroid() {
+        // give one node an initial velocity so the whole layout drifts
+        let (mut nodes, edges) = setup();
+        nodes[0].velocity = Vector2D::from_xy(1.0, 1.0);
+        let mut fs = ForceSimulation::new(nodes.clone(), edges.clone(), 0.01, 1.0, 1.0);
+        let initial_com = fs.center_of_mass();
+        fs.step_n(10);
+        assert!(fs.center_of_mass().distance(&initial_com) > 1e-3);
+
+        let mut fs = ForceSimulation::new(nodes, edges, 0.01, 1.0, 1.0);
+        fs.lock_centroid(true);
+        let initial_separation = fs.get_nodes()[0]
+            .position
+            .distance(&fs.get_nodes()[1].position);
+        for _ in 0..10 {
+            fs.step();
+            assert!(fs.center_of_mass().distance(&initial_com) < 1e-10);
+        }
+
+        // the nodes still moved relative to each other
+        let separation = fs.get_nodes()[0]
+            .position
+            .distance(&fs.get_nodes()[1].position);
+        assert!((separation - initial_separation).abs() > 1e-3);
+    }
+
+    #[test]
+    pub fn test_lock_centroid_with_fixed_node() {
+        let (mut nodes, edges) = setup();
+        nodes[0].velocity = Vector2D::from_xy(1.0, 1.0);
+        nodes[2].fixed = true;
+        let fixed_position = nodes[2].position;
+        let mut fs = ForceSimulation::new(nodes, edges, 0.01, 1.0, 1.0);
+        fs.lock_centroid(true);
+        let initial_com = fs.center_of_mass();
+        for _ in 0..10 {
+            fs.step();
+            assert!(fs.center_of_mass().distance(&initial_com) < 1e-10);
+            assert_eq!(fs.get_nodes()[2].position, fixed_position);
+        }
+    }
+
+    #[test]
+    pub fn test_remove_angular_momentum() {
+        // net angular momentum of the layout about its center of mass
+        let angular_momentum = |fs: &ForceSimulation| {
+            let center = fs.center_of_mass();
+            fs.get_nodes()
+                .iter()
+                .map(|node| node.mass * (node.position - center).cross(&node.velocity))
+                .sum::<f64>()
+        };
+
+        // set the layout spinning counterclockwise about its center of mass
+        let (mut nodes, edges) = setup();
+        let center =
+            ForceSimulation::new(nodes.clone(), edges.clone(), 0.01, 1.0, 1.0).center_of_mass();
+        for node in nodes.iter_mut() {
+            node.velocity = (node.position - center).orthogonal();
+        }
+
+        let mut fs = ForceSimulation::new(nodes.clone(), edges.clone(), 0.01, 1.0, 1.0);
+        fs.step_n(10);
+        assert!(angular_momentum(&fs) > 1e-3);
+
+        let mut fs = ForceSimulation::new(nodes, edges, 0.01, 1.0, 1.0);
+        fs.lock_centroid(true);
+        fs.remove_angular_momentum(true);
+        let initial_separation = fs.get_nodes()[0]
+            .position
+            .distance(&fs.get_nodes()[1].position);
+        for _ in 0..10 {
+            fs.step();
+            assert!(angular_momentum(&fs).abs() < 1e-10);
+        }
+
+        // the nodes still moved relative to each other
+        let separation = fs.get_nodes()[0]
+            .position
+            .distance(&fs.get_nodes()[1].position);
+        assert!((separation - initial_separation).abs() > 1e-3);
+    }
+
+    #[test]
+    pub fn test_add_anchor() {
+        let (nodes, edges) = setup();
+        let params = SimParams {
+            time_step: 0.05,
+            damping: 0.5,
+            ..SimParams::default()
+        };
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, params);
+        let target = Vector2D::from_xy(5.0, 5.0);
+        fs.add_anchor(0, target, 50.0);
+
+        let initial_distance = fs.get_nodes()[0].position.distance(&target);
+        let mut previous = fs.get_nodes()[0].position;
+        for _ in 0..20 {
+            fs.step();
+
+            // the node keeps moving rather than being frozen or snapped to the target
+            let position = fs.get_nodes()[0].position;
+            assert_ne!(position, previous);
+            assert_ne!(position, target);
+            previous = position;
+        }
+        assert!(fs.get_nodes()[0].position.distance(&target) < initial_distance / 2.0);
+
+        // without the anchor, the node is not pulled toward the target
+        fs.clear_anchors();
+        assert_eq!(fs.external_acceleration(0), Vector2D::from_xy(0.0, 0.0));
+    }
+
+    #[test]
+    pub fn test_set_group_strength() {
+        // two groups side by side, each initially farther from its own member than from the
+        // nearest member of the other group
+        let corners = [(0.0, 0.0, 0), (2.0, 0.0, 1), (2.0, 3.0, 1), (0.0, 3.0, 0)];
+        let nodes: Vec<Node> = corners
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, y, group))| {
+                Node::new()
+                    .id(i)
+                    .position(Vector2D::from_xy(x, y))
+                    .group(group)
+                    .build()
+            })
+            .collect();
+        let mut edges = vec![];
+        for i in 0..nodes.len() {
+            for j in (i + 1)..nodes.len() {
+                edges.push(Edge::new(i, j, 1.0));
+            }
+        }
+        let params = SimParams {
+            time_step: 0.1,
+            damping: 0.5,
+            temperature: Some(0.1),
+            ..SimParams::default()
+        };
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, params);
+        fs.set_group_strength(20.0, 2.0);
+        fs.step_n(30);
+
+        let nodes = fs.get_nodes();
+        let distance = |i: usize, j: usize| nodes[i].position.distance(&nodes[j].position);
+        let intra = distance(0, 3).max(distance(1, 2));
+        let inter = distance(0, 1)
+            .min(distance(0, 2))
+            .min(distance(3, 1))
+            .min(distance(3, 2));
+        assert!(
+            intra < inter,
+            "intra {} should be below inter {}",
+            intra,
+            inter
+        );
+    }
+
+    #[test]
+    pub fn test_velocity_verlet_conserves_energy() {
+        // two nodes whose net pairwise force is attractive, orbiting each other
+        let orbiting = |integrator: Integrator| {
+            let nodes = vec![
+                Node::new()
+                    .position(Vector2D::from_xy(-1.0, 0.0))
+                    .velocity(Vector2D::from_xy(0.0, -0.4))
+                    .build(),
+                Node::new()
+                    .id(1)
+                    .position(Vector2D::from_xy(1.0, 0.0))
+                    .velocity(Vector2D::from_xy(0.0, 0.4))
+                    .build(),
+            ];
+            let params = SimParams {
+                time_step: 0.05,
+                attraction_constant: 0.25,
+                integrator,
+                ..SimParams::default()
+            };
+            ForceSimulation::new_with_params(nodes, vec![Edge::new(0, 1, 1.0)], params)
+        };
+        let total_energy = |fs: &ForceSimulation| fs.system_energy() + fs.kinetic_energy();
+        let drift = |mut fs: ForceSimulation| {
+            let initial = total_energy(&fs);
+            fs.step_n(500);
+            ((total_energy(&fs) - initial) / initial).abs()
+        };
+
+        let verlet_drift = drift(orbiting(Integrator::VelocityVerlet));
+        let euler_drift = drift(orbiting(Integrator::Euler));
+        assert!(verlet_drift < 1e-3, "verlet drift {}", verlet_drift);
+        assert!(
+            euler_drift > 10.0 * verlet_drift,
+            "euler drift {}",
+            euler_drift
+        );
+    }
+
+    #[test]
+    pub fn test_verlet_reuses_end_of_step_forces() {
+        let (nodes, edges) = fully_connected(5);
+        let params = SimParams {
+            integrator: Integrator::VelocityVerlet,
+            ..SimParams::default()
+        };
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, params);
+        let mut recomputed = fs.clone();
+        assert!(!fs.reusable_forces());
+        for _ in 0..20 {
+            fs.step();
+            assert!(fs.reusable_forces());
+            // forces computed from scratch at the start of every step give the same layout
+            recomputed.invalidate_forces();
+            recomputed.step();
+        }
+        assert_eq!(fs.export_positions(), recomputed.export_positions());
+        assert_eq!(fs.last_forces(), recomputed.last_forces());
+
+        fs.add_anchor(0, Vector2D::from_xy(0.0, 0.0), 1.0);
+        assert!(!fs.reusable_forces());
+        fs.step();
+        assert!(fs.reusable_forces());
+        fs.add_jitter_from_state(0.1);
+        assert!(!fs.reusable_forces());
+    }
+
+    #[test]
+    pub fn test_force_exponents() {
+        // the spacing two connected nodes settle at, for the given exponents
+        let equilibrium_spacing = |attraction_exponent: f64, repulsion_exponent: f64| {
+            let nodes = vec![
+                Node::new().position(Vector2D::from_xy(0.0, 0.0)).build(),
+                Node::new()
+                    .id(1)
+                    .position(Vector2D::from_xy(1.5, 0.0))
+                    .build(),
+            ];
+            let params = SimParams {
+                time_step: 0.05,
+                repulsion_constant: 8.0,
+                attraction_exponent,
+                repulsion_exponent,
+                damping: 0.2,
+                ..SimParams::default()
+            };
+            let mut fs =
+                ForceSimulation::new_with_params(nodes, vec![Edge::new(0, 1, 1.0)], params);
+            fs.step_n(2000);
+            fs.get_nodes()[0]
+                .position
+                .distance(&fs.get_nodes()[1].position)
+        };
+
+        // linear springs against inverse-square repulsion balance where d^3 = 8
+        let springs = equilibrium_spacing(1.0, -2.0);
+        assert!(approx_equal(springs, 2.0, 1e-3), "spacing {}", springs);
+
+        // attraction growing faster with distance pulls the nodes closer together
+        let stiffer = equilibrium_spacing(2.0, -2.0);
+        assert!(approx_equal(stiffer, 8.0_f64.powf(0.25), 1e-3));
+        assert!(stiffer < springs);
+
+        // repulsion falling off more slowly pushes them further apart
+        let wider = equilibrium_spacing(1.0, -1.0);
+        assert!(approx_equal(wider, 8.0_f64.sqrt(), 1e-3));
+        assert!(wider > springs);
+    }
+
+    #[test]
+    pub fn test_rotate_nodes() {
+        let (mut nodes, edges) = setup();
+        let fourth = Vector2D::from_xy(5.0, 5.0);
+        nodes.push(Node::new().id(4).position(fourth).build());
+        let mut fs = ForceSimulation::new(nodes, edges, 1.0, 1.0, 1.0);
+
+        let pivot = Vector2D::from_xy(1.0, 1.0);
+        fs.rotate_nodes(&[0, 1, 2], PI / 2.0, pivot);
+
+        // (0, 0), (1, 0) and (0, 1) turn a quarter counterclockwise about (1, 1)
+        let nodes = fs.get_nodes();
+        assert_vec_approx!(nodes[0].position, Vector2D::from_xy(2.0, 0.0), 1e-10);
+        assert_vec_approx!(nodes[1].position, Vector2D::from_xy(2.0, 1.0), 1e-10);
+        assert_vec_approx!(nodes[2].position, Vector2D::from_xy(1.0, 0.0), 1e-10);
+
+        // nodes outside the selection stay put
+        assert_eq!(nodes[3].position, fourth);
+        assert_eq!(fs.positions[0], nodes[0].position);
+    }
+
+    #[test]
+    pub fn test_weight_semantics_distance() {
+        // the length a single edge of the given weight settles at
+        let settled_length = |weight: f64| {
+            let nodes = vec![
+                Node::new().position(Vector2D::from_xy(0.0, 0.0)).build(),
+                Node::new()
+                    .id(1)
+                    .position(Vector2D::from_xy(1.5, 0.0))
+                    .build(),
+            ];
+            let params = SimParams {
+                time_step: 0.05,
+                repulsion_constant: 0.1,
+                damping: 0.2,
+                weight_semantics: WeightSemantics::Distance,
+                ..SimParams::default()
+            };
+            let mut fs =
+                ForceSimulation::new_with_params(nodes, vec![Edge::new(0, 1, weight)], params);
+            fs.step_n(2000);
+            fs.get_nodes()[0]
+                .position
+                .distance(&fs.get_nodes()[1].position)
+        };
+
+        // heavier edges settle longer, close to their weight when repulsion is weak
+        let short = settled_length(1.0);
+        let long = settled_length(3.0);
+        assert!(long > short);
+        assert!(approx_equal(short, 1.0, 0.2), "short {}", short);
+        assert!(approx_equal(long, 3.0, 0.2), "long {}", long);
+    }
+
+    #[test]
+    pub fn test_node_pairs() {
+        let (nodes, edges) = fully_connected(6);
+        let fs = ForceSimulation::new(nodes, edges, 1.0, 1.0, 1.0);
+        let pairs: Vec<(usize, usize)> = fs.node_pairs().collect();
+        assert_eq!(pairs.len(), 6 * 5 / 2);
+        assert!(pairs.iter().all(|&(i, j)| i < j && j < 6));
+
+        let mut unique = pairs.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), pairs.len());
+
+        let (nodes, edges) = setup();
+        let single = ForceSimulation::new(nodes[..1].to_vec(), vec![], 1.0, 1.0, 1.0);
+        assert_eq!(single.node_pairs().count(), 0);
+    }
+
+    #[test]
+    pub fn test_fallible_constructors_and_lookups() {
+        let (nodes, mut edges) = setup();
+        edges.push(Edge::new(1, 3, 1.0));
+        assert!(matches!(
+            ForceSimulation::try_new_with_params(nodes.clone(), edges, SimParams::default()),
+            Err(Error::DanglingNodeIndex(3))
+        ));
+
+        let (_, edges) = setup();
+        let fs =
+            ForceSimulation::try_new_with_params(nodes, edges[..2].to_vec(), SimParams::default())
+                .unwrap();
+        assert_eq!(fs.edge_between(2, 0).unwrap().weight, 2.0);
+        assert!(matches!(
+            fs.edge_between(1, 2),
+            Err(Error::MissingEdge(1, 2))
+        ));
+
+        assert!(matches!(
+            ForceSimulation::from_json("{\"nodes\": 5}"),
+            Err(Error::Serde(_))
+        ));
+    }
+
+    #[test]
+    pub fn test_from_json_validated() {
+        let json = r#"{
+            "nodes": [
+                {"id": 0, "position": {"x": 0.0, "y": 0.0}},
+                {"id": 1, "position": {"x": 1.0}}
+            ],
+            "edges": [
+                {"node1_idx": 0, "node2_idx": 5, "weight": 1.0},
+                {"node1_idx": 0, "weight": 1.0}
+            ]
+        }"#;
+        let Err(Error::InvalidFields(errors)) = ForceSimulation::from_json_validated(json) else {
+            panic!("expected field errors");
+        };
+        assert_eq!(
+            errors,
+            vec![
+                FieldError::new("nodes[1]", "position.y", "expected a number"),
+                FieldError::new("edges[0]", "node2_idx", "index 5 is out of range (2 nodes)"),
+                FieldError::new("edges[1]", "node2_idx", "missing"),
+            ]
+        );
+
+        // negative weights are only an error when they are target distances
+        let json = r#"{
+            "nodes": [
+                {"id": 0, "position": {"x": 0.0, "y": 0.0}},
+                {"id": 1, "position": {"x": 1.0, "y": 0.0}}
+            ],
+            "edges": [{"node1_idx": 0, "node2_idx": 1, "weight": -0.5}]
+        }"#;
+        let fs = ForceSimulation::from_json_validated(json).unwrap();
+        assert_eq!(fs.n_nodes(), 2);
+
+        let Err(Error::InvalidFields(errors)) = ForceSimulation::from_json_validated("{}") else {
+            panic!("expected field errors");
+        };
+        assert_eq!(
+            errors,
+            vec![
+                FieldError::new("", "nodes", "missing"),
+                FieldError::new("", "edges", "missing"),
+            ]
+        );
+        assert_eq!(fs.edge_between(0, 1).unwrap().weight, -0.5);
+
+        let json = json.replacen('{', r#"{"weight_semantics": "Distance","#, 1);
+        let Err(Error::InvalidFields(errors)) = ForceSimulation::from_json_validated(&json) else {
+            panic!("expected field errors");
+        };
+        assert_eq!(
+            errors,
+            vec![FieldError::new(
+                "edges[0]",
+                "weight",
+                "negative target distance -0.5"
+            )]
+        );
+    }
+
+    #[test]
+    pub fn test_rejects_degenerate_toroidal_canvas() {
+        let (nodes, edges) = setup();
+        let params = |width: f64, height: f64| SimParams {
+            distance_metric: DistanceMetric::Toroidal { width, height },
+            ..SimParams::default()
+        };
+        assert!(matches!(
+            ForceSimulation::try_new_with_params(nodes.clone(), edges.clone(), params(0.0, 0.0)),
+            Err(Error::InvalidCanvasSize(_, _))
+        ));
+        let mut fs =
+            ForceSimulation::try_new_with_params(nodes, edges, params(10.0, 10.0)).unwrap();
+        fs.step();
+        assert!(fs
+            .export_positions()
+            .iter()
+            .all(|&(_, x, y)| x.is_finite() && y.is_finite()));
+
+        let json = r#"{
+            "nodes": [{"id": 0, "position": {"x": 0.0, "y": 0.0}}],
+            "edges": [],
+            "distance_metric": {"Toroidal": {"width": 0.0, "height": 5.0}}
+        }"#;
+        let Err(Error::InvalidFields(errors)) = ForceSimulation::from_json_validated(json) else {
+            panic!("expected field errors");
+        };
+        assert_eq!(
+            errors,
+            vec![FieldError::new(
+                "",
+                "distance_metric",
+                "canvas size 0 x 5 is not positive and finite"
+            )]
+        );
+        assert!(matches!(
+            ForceSimulation::from_json(json),
+            Err(Error::InvalidCanvasSize(_, _))
+        ));
+    }
+
+    #[test]
+    pub fn test_warmup_steps() {
+        // the largest distance any node moves in a single one of the first 20 steps
+        let max_displacement = |warmup: usize| {
+            let nodes = vec![
+                Node::new().position(Vector2D::from_xy(0.0, 0.0)).build(),
+                Node::new()
+                    .id(1)
+                    .position(Vector2D::from_xy(0.01, 0.0))
+                    .build(),
+                Node::new()
+                    .id(2)
+                    .position(Vector2D::from_xy(0.0, 0.01))
+                    .build(),
+            ];
+            let (_, edges) = setup();
+            let params = SimParams {
+                time_step: 0.1,
+                damping: 0.5,
+                ..SimParams::default()
+            };
+            let mut fs = ForceSimulation::new_with_params(nodes, edges, params);
+            fs.warmup_steps(warmup);
+
+            let mut max_displacement: f64 = 0.0;
+            for _ in 0..20 {
+                let before: Vec<Vector2D<f64>> =
+                    fs.get_nodes().iter().map(|n| n.position).collect();
+                fs.step();
+                for (node, previous) in fs.get_nodes().iter().zip(before) {
+                    max_displacement = max_displacement.max(node.position.distance(&previous));
+                }
+            }
+            max_displacement
+        };
+
+        let without = max_displacement(0);
+        let with = max_displacement(10);
+        assert!(without > 100.0, "without warmup {}", without);
+        assert!(with < 10.0, "with warmup {}", with);
+    }
+
+    #[test]
+    pub fn test_current_time_step() {
+        let mut fs = get_force_simulation();
+        fs.warmup_steps(3);
+        let mut time_steps = vec![];
+        for _ in 0..5 {
+            let forces = fs.compute_forces();
+            time_steps.push(fs.current_time_step(&forces));
+            fs.steps_taken += 1;
+        }
+        assert_eq!(time_steps, vec![0.25, 0.5, 0.75, 1.0, 1.0]);
+    }
+
+    #[test]
+    pub fn test_step_sanitizes_non_finite_positions() {
+        let (mut nodes, edges) = setup();
+        nodes[1].velocity = Vector2D::from_xy(f64::NAN, 0.0);
+        let mut fs = ForceSimulation::new(nodes, edges, 0.1, 1.0, 1.0);
+        fs.step();
+
+        let node = &fs.get_nodes()[1];
+        assert!(node.position.is_finite());
+        assert!(node.velocity.is_finite());
+        assert_eq!(node.position.x, 1.0);
+
+        // the NaN does not spread to the other nodes on later steps
+        fs.step_n(5);
+        assert!(fs.get_nodes().iter().all(|node| node.position.is_finite()));
+    }
+
+    #[test]
+    pub fn test_steps_are_bitwise_reproducible() {
+        // forces are summed in a fixed order, by source node index, so identical simulations
+        // must agree exactly rather than approximately
+        let run = |deterministic: bool| {
+            let (nodes, edges) = fully_connected(8);
+            let params = SimParams {
+                time_step: 0.01,
+                damping: 0.1,
+                seed: 3,
+                deterministic,
+                ..SimParams::default()
+            };
+            let mut fs = ForceSimulation::new_with_params(nodes, edges, params);
+            assert_eq!(fs.is_deterministic(), deterministic);
+            fs.add_jitter_from_state(0.5);
+            fs.step_n(50);
+            fs.get_nodes()
+                .iter()
+                .map(|node| (node.position.x.to_bits(), node.position.y.to_bits()))
+                .collect::<Vec<(u64, u64)>>()
+        };
+        assert_eq!(run(true), run(true));
+        // the sequential sum already follows that order, so the mode changes nothing yet
+        assert_eq!(run(true), run(false));
+    }
+
+    #[test]
+    pub fn test_n_nodes_and_n_edges() {
+        let fs = get_force_simulation();
+        assert_eq!(fs.n_nodes(), 3);
+        assert_eq!(fs.n_edges(), 3);
+        assert!(!fs.is_empty());
+
+        let (nodes, edges) = fully_connected(5);
+        let fs = ForceSimulation::new(nodes, edges, 1.0, 1.0, 1.0);
+        assert_eq!(fs.n_nodes(), 5);
+        assert_eq!(fs.n_edges(), 10);
+        assert_eq!(fs.n_nodes(), fs.get_nodes().len());
+        assert_eq!(fs.n_edges(), fs.get_edges().len());
+
+        let empty = ForceSimulation::new(vec![], vec![], 1.0, 1.0, 1.0);
+        assert_eq!(empty.n_nodes(), 0);
+        assert_eq!(empty.n_edges(), 0);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    pub fn test_from_json_versions() {
+        let (nodes, edges) = setup();
+        let params = SimParams {
+            seed: 9,
+            integrator: Integrator::VelocityVerlet,
+            ..SimParams::default()
+        };
+        let fs = ForceSimulation::new_with_params(nodes, edges, params);
+        let json = fs.to_json().unwrap();
+        assert!(json.contains(&format!("\"version\":{}", SERIALIZATION_VERSION)));
+
+        // a version 1 state has no version tag and none of the fields added since
+        let mut v1: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let object = v1.as_object_mut().unwrap();
+        for field in [
+            "version",
+            "seed",
+            "integrator",
+            "weight_semantics",
+            "warmup_steps",
+            "steps_taken",
+            "anchors",
+            "attraction_exponent",
+        ] {
+            object.remove(field);
+        }
+        let restored = ForceSimulation::from_json(&v1.to_string()).unwrap();
+        assert_eq!(restored.version, SERIALIZATION_VERSION);
+        assert_eq!(restored.seed(), 0);
+        // states from before version 3 defaulted to inverse-square attraction
+        assert_eq!(restored.attraction_exponent, -2.0);
+        assert_eq!(restored.integrator, Integrator::Euler);
+        assert_eq!(restored.n_nodes(), 3);
+        assert_eq!(restored.edge_index, fs.edge_index);
+        assert_eq!(restored.intra_group_strength, 1.0);
+
+        // a current state that leaves the exponent out takes today's default
+        let mut current: serde_json::Value = serde_json::from_str(&json).unwrap();
+        current
+            .as_object_mut()
+            .unwrap()
+            .remove("attraction_exponent");
+        let restored = ForceSimulation::from_json(&current.to_string()).unwrap();
+        assert_eq!(
+            restored.attraction_exponent,
+            SimParams::default().attraction_exponent
+        );
+
+        // a state from a future version is rejected
+        let mut future: serde_json::Value = serde_json::from_str(&json).unwrap();
+        future["version"] = serde_json::json!(SERIALIZATION_VERSION + 1);
+        assert!(matches!(
+            ForceSimulation::from_json(&future.to_string()),
+            Err(Error::UnsupportedVersion(v)) if v == SERIALIZATION_VERSION + 1
+        ));
+    }
+
+    #[test]
+    pub fn test_from_json_matches_new_with_params() {
+        // a parallel edge, merged when cleaning, and an isolated node with a mass of its own
+        let nodes = vec![
+            Node::at(0, Vector2D::from_xy(0.0, 0.0)),
+            Node::at(1, Vector2D::from_xy(1.0, 0.0)),
+            Node::at(2, Vector2D::from_xy(0.0, 1.0)),
+            Node::new()
+                .id(3)
+                .position(Vector2D::from_xy(5.0, 5.0))
+                .mass(4.0)
+                .build(),
+        ];
+        let edges = vec![
+            Edge::new(0, 1, 1.0),
+            Edge::new(0, 2, 2.0),
+            Edge::new(1, 0, 1.0),
+        ];
+        let json = r#"{
+            "clean_edges": true,
+            "nodes": [
+                {"id": 0, "position": {"x": 0.0, "y": 0.0}},
+                {"id": 1, "position": {"x": 1.0, "y": 0.0}},
+                {"id": 2, "position": {"x": 0.0, "y": 1.0}},
+                {"id": 3, "position": {"x": 5.0, "y": 5.0}, "mass": 4.0}
+            ],
+            "edges": [
+                {"node1_idx": 0, "node2_idx": 1, "weight": 1.0},
+                {"node1_idx": 0, "node2_idx": 2, "weight": 2.0},
+                {"node1_idx": 1, "node2_idx": 0, "weight": 1.0}
+            ]
+        }"#;
+        let params = SimParams {
+            clean_edges: true,
+            ..SimParams::default()
+        };
+        let created = ForceSimulation::new_with_params(nodes, edges, params);
+        let masses = |fs: &ForceSimulation| -> Vec<f64> {
+            fs.get_nodes().iter().map(|node| node.mass).collect()
+        };
+        assert_eq!(masses(&created), vec![4.0, 2.0, 2.0, 4.0]);
+
+        for loaded in [
+            ForceSimulation::from_json(json).unwrap(),
+            ForceSimulation::from_json_validated(json).unwrap(),
+            ForceSimulation::from_json(&created.to_json().unwrap()).unwrap(),
+        ] {
+            assert_eq!(masses(&loaded), masses(&created));
+            assert_eq!(loaded.masses, created.masses);
+            assert_eq!(loaded.get_edges(), created.get_edges());
+            assert_eq!(loaded.compute_forces(), created.compute_forces());
+        }
+    }
+
+    #[test]
+    pub fn test_json_format() {
+        let (nodes, edges) = setup();
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, SimParams::default());
+        fs.step();
+
+        // the caches derived from the nodes and edges are left out, and rebuilt on load
+        let json: serde_json::Value = serde_json::from_str(&fs.to_json().unwrap()).unwrap();
+        for cache in [
+            "positions",
+            "velocities",
+            "masses",
+            "edge_index",
+            "last_forces",
+        ] {
+            assert!(json.get(cache).is_none(), "{} was serialized", cache);
+        }
+        let restored = ForceSimulation::from_json(&json.to_string()).unwrap();
+        assert_eq!(restored.positions.len(), 3);
+        assert_eq!(restored.edge_index, fs.edge_index);
+        assert_eq!(restored.degrees, fs.degrees);
+
+        // the nodes and edges are required, but any setting can be left out
+        assert!(matches!(
+            ForceSimulation::from_json("{}"),
+            Err(Error::Serde(_))
+        ));
+        assert!(matches!(
+            ForceSimulation::from_json(r#"{"nodes": []}"#),
+            Err(Error::Serde(_))
+        ));
+        let minimal = ForceSimulation::from_json(r#"{"nodes": [], "edges": []}"#).unwrap();
+        assert_eq!(minimal.time_step, SimParams::default().time_step);
+        assert_eq!(minimal.intra_group_strength, 1.0);
+        assert_eq!(minimal.min_distance, DEFAULT_MIN_DISTANCE);
+    }
+
+    #[test]
+    pub fn test_bounding_box() {
+        let fs = get_force_simulation();
+        assert_eq!(
+            fs.bounding_box(),
+            Some((Vector2D::from_xy(0.0, 0.0), Vector2D::from_xy(1.0, 1.0)))
+        );
+        let empty = ForceSimulation::new(vec![], vec![], 1.0, 1.0, 1.0);
+        assert_eq!(empty.bounding_box(), None);
+    }
+
+    #[test]
+    pub fn test_nodes_within() {
+        let nodes = vec![
+            Node::new().position(Vector2D::from_xy(0.0, 0.0)).build(),
+            Node::new().position(Vector2D::from_xy(3.0, 4.0)).build(),
+            Node::new().position(Vector2D::from_xy(1.0, 1.0)).build(),
+        ];
+        let fs = ForceSimulation::new(nodes, vec![], 0.01, 1.0, 1.0);
+        let origin = Vector2D::from_xy(0.0, 0.0);
+
+        assert_eq!(fs.nodes_within(origin, 10.0), vec![0, 1, 2]);
+        // a node exactly on the boundary is included
+        assert_eq!(fs.nodes_within(origin, 5.0), vec![0, 1, 2]);
+        assert_eq!(fs.nodes_within(origin, 4.99), vec![0, 2]);
+        assert_eq!(fs.nodes_within(origin, 0.0), vec![0]);
+        assert!(fs.nodes_within(origin, -1.0).is_empty());
+        assert_eq!(fs.nodes_within(Vector2D::from_xy(3.0, 3.0), 1.0), vec![1]);
+    }
+
+    #[test]
+    pub fn test_sample_force_field() {
+        let center = Vector2D::from_xy(2.0, 3.0);
+        let heavy = Node::new().position(center).mass(10.0).build();
+        let fs = ForceSimulation::new(vec![heavy], vec![], 1.0, 1.0, 1.0);
+
+        let samples = fs.sample_force_field(5);
+        assert_eq!(samples.len(), 25);
+
+        // the lattice spans the node, padded by 1 on each side
+        assert_eq!(samples[0].0, Vector2D::from_xy(1.0, 2.0));
+        assert_eq!(samples[24].0, Vector2D::from_xy(3.0, 4.0));
+
+        for (position, force) in samples.iter() {
+            if *position == center {
+                // a probe on the node itself feels nothing
+                assert_eq!(*force, Vector2D::from_xy(0.0, 0.0));
+                continue;
+            }
+
+            // repulsion points radially away from the node
+            let outward = (*position - center).normalize();
+            assert_vec_approx!(force.normalize(), outward, 1e-10);
+
+            // and falls off with distance
+            let distance = position.distance(&center);
+            assert!(approx_equal(
+                force.magnitude(),
+                10.0 / distance.powi(2),
+                1e-10
+            ));
+        }
+
+        assert!(fs.sample_force_field(0).is_empty());
+    }
+
+    #[test]
+    pub fn test_step_report() {
+        let (nodes, edges) = setup();
+        let params = SimParams {
+            time_step: 0.1,
+            temperature: Some(0.5),
+            ..SimParams::default()
+        };
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, params);
+        let before: Vec<Vector2D<f64>> = fs.get_nodes().iter().map(|n| n.position).collect();
+
+        let report = fs.step();
+        let moves: Vec<f64> = fs
+            .get_nodes()
+            .iter()
+            .zip(before)
+            .map(|(node, previous)| node.position.distance(&previous))
+            .collect();
+
+        assert_eq!(
+            report.max_displacement,
+            moves.iter().cloned().fold(0.0, f64::max)
+        );
+        assert!(report.max_displacement > 0.0);
+        assert!(approx_equal(
+            report.total_movement,
+            moves.iter().sum(),
+            1e-12
+        ));
+        assert_eq!(report.kinetic_energy, fs.kinetic_energy());
+        assert_eq!(report.temperature, Some(0.5));
+    }
+
+    #[test]
+    pub fn test_normalize_layout_stretch() {
+        let nodes = vec![
+            Node::new().position(Vector2D::from_xy(0.0, 0.0)).build(),
+            Node::new().position(Vector2D::from_xy(4.0, 2.0)).build(),
+            Node::new().position(Vector2D::from_xy(1.0, 1.0)).build(),
+        ];
+        let mut fs = ForceSimulation::new(nodes, vec![], 1.0, 1.0, 1.0);
+
+        let target = (Vector2D::from_xy(0.0, 0.0), Vector2D::from_xy(100.0, 100.0));
+        fs.normalize_layout(target, false);
+
+        // the layout fills the box exactly
+        assert_eq!(fs.bounding_box(), Some(target));
+        assert_vec_approx!(
+            fs.get_nodes()[2].position,
+            Vector2D::from_xy(25.0, 50.0),
+            1e-10
+        );
+        assert_eq!(fs.positions[2], fs.get_nodes()[2].position);
+    }
+
+    #[test]
+    pub fn test_normalize_layout_preserve_aspect() {
+        let nodes = vec![
+            Node::new().position(Vector2D::from_xy(0.0, 0.0)).build(),
+            Node::new().position(Vector2D::from_xy(4.0, 2.0)).build(),
+            Node::new().position(Vector2D::from_xy(1.0, 1.0)).build(),
+        ];
+        let mut fs = ForceSimulation::new(nodes, vec![], 1.0, 1.0, 1.0);
+
+        let target = (Vector2D::from_xy(0.0, 0.0), Vector2D::from_xy(100.0, 100.0));
+        fs.normalize_layout(target, true);
+
+        // the wider x axis fills the box, and y is scaled by the same factor and centered
+        let (min, max) = fs.bounding_box().unwrap();
+        assert_vec_approx!(min, Vector2D::from_xy(0.0, 25.0), 1e-10);
+        assert_vec_approx!(max, Vector2D::from_xy(100.0, 75.0), 1e-10);
+        assert_vec_approx!(
+            fs.get_nodes()[2].position,
+            Vector2D::from_xy(25.0, 50.0),
+            1e-10
+        );
+
+        // a single node lands in the middle of the box
+        let single = vec![Node::new().position(Vector2D::from_xy(3.0, -7.0)).build()];
+        let mut fs = ForceSimulation::new(single, vec![], 1.0, 1.0, 1.0);
+        fs.normalize_layout(target, true);
+        assert_eq!(fs.get_nodes()[0].position, Vector2D::from_xy(50.0, 50.0));
+    }
+
+    #[test]
+    pub fn test_compute_forces() {
+        let nodes = vec![
+            Node::new().position(Vector2D::from_xy(0.0, 0.0)).build(),
+            Node::new().position(Vector2D::from_xy(2.0, 0.0)).build(),
+        ];
+        let params = SimParams {
+            attraction_constant: 0.375,
+            gravity: 0.1,
+            ..SimParams::default()
+        };
+        let fs = ForceSimulation::new_with_params(nodes, vec![Edge::new(0, 1, 2.0)], params);
+
+        // both masses are the edge weight 2, so attraction 0.375 * 2 * 2 = 1.5 and repulsion
+        // 1 * 2 * 2 / 2^2 = 1 leave a net pull of 0.5, and gravity pulls the second node back
+        // toward the origin with 0.1 * 2 * 2 = 0.4
+        let forces = fs.compute_forces();
+        assert_eq!(forces.len(), 2);
+        assert_vec_approx!(forces[0], Vector2D::from_xy(0.5, 0.0), 1e-12);
+        assert_vec_approx!(forces[1], Vector2D::from_xy(-0.9, 0.0), 1e-12);
+
+        // computing the forces leaves the simulation untouched
+        assert_eq!(fs.compute_forces(), forces);
+        assert_eq!(fs.get_nodes()[1].position, Vector2D::from_xy(2.0, 0.0));
+    }
+
+    #[test]
+    pub fn test_net_force_sums_pairwise() {
+        let fs = get_force_simulation();
+        let nodes = fs.get_nodes();
+        let weight = |i: usize, j: usize| fs.get_edge_connecting_nodes(i, j).unwrap().weight;
+        let force_on =
+            |j: usize, k: usize| fs.total_force_n1_exerts_on_n2(&nodes[j], &nodes[k], weight(j, k));
+
+        // the net force on each node is the hand-summed force from each of the other two
+        let forces = fs.compute_forces();
+        assert_vec_approx!(forces[0], force_on(1, 0) + force_on(2, 0), 1e-12);
+        assert_vec_approx!(forces[1], force_on(0, 1) + force_on(2, 1), 1e-12);
+        assert_vec_approx!(forces[2], force_on(0, 2) + force_on(1, 2), 1e-12);
+
+        // each pairwise force has an equal and opposite reaction, so the net forces cancel
+        for (i, j) in fs.node_pairs() {
+            assert_vec_approx!(force_on(i, j), -force_on(j, i), 1e-12);
+        }
+        let total = forces
+            .iter()
+            .fold(Vector2D::from_xy(0.0, 0.0), |total, &force| total + force);
+        assert_vec_approx!(total, Vector2D::from_xy(0.0, 0.0), 1e-12);
+    }
+
+    #[test]
+    pub fn test_integrate_matches_step() {
+        let (nodes, edges) = setup();
+        let mut stepped = ForceSimulation::new(nodes, edges, 0.1, 1.0, 1.0);
+        let mut integrated = stepped.clone();
+
+        stepped.step();
+        let forces = integrated.compute_forces();
+        integrated.integrate(&forces);
+        for (a, b) in stepped.get_nodes().iter().zip(integrated.get_nodes()) {
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.velocity, b.velocity);
+        }
+        assert_eq!(stepped.last_forces(), &forces);
+
+        // with no force, a node just coasts
+        let moving = Node::new()
+            .position(Vector2D::from_xy(0.0, 0.0))
+            .velocity(Vector2D::from_xy(1.0, 0.0))
+            .build();
+        let mut fs = ForceSimulation::new(vec![moving], vec![], 0.5, 1.0, 1.0);
+        fs.integrate(&[Vector2D::from_xy(0.0, 0.0)]);
+        assert_eq!(fs.get_nodes()[0].position, Vector2D::from_xy(0.5, 0.0));
+    }
+
+    #[test]
+    pub fn test_translate_fixed() {
+        let (mut nodes, mut edges) = setup();
+        nodes.push(
+            Node::new()
+                .id(4)
+                .position(Vector2D::from_xy(1.0, 1.0))
+                .build(),
+        );
+        edges.push(Edge::new(0, 3, 1.0));
+        edges.push(Edge::new(1, 3, 1.0));
+        edges.push(Edge::new(2, 3, 1.0));
+        let mut fs = ForceSimulation::new(nodes, edges, 0.1, 1.0, 1.0);
+        let before: Vec<Vector2D<f64>> = fs.get_nodes().iter().map(|n| n.position).collect();
 
-        let distance = 1.0;
-        let weight = 1.0;
-        let direction = 0.0;
+        fs.set_fixed(&[0, 1], true);
+        let delta = Vector2D::from_xy(0.5, -0.25);
+        fs.translate_fixed(&[0, 1], delta);
+        fs.step();
 
-        let expected = Vector2D::from_xy(1.0, 0.0);
-        let actual = force_simulation.attractive_force_n1_exerts_on_n2(distance, weight, direction);
-        assert_eq!(actual, expected);
+        // the fixed pair moved exactly by delta, while the others responded to the forces
+        let nodes = fs.get_nodes();
+        assert_eq!(nodes[0].position, before[0] + delta);
+        assert_eq!(nodes[1].position, before[1] + delta);
+        assert_eq!(nodes[0].velocity, Vector2D::from_xy(0.0, 0.0));
+        assert_ne!(nodes[2].position, before[2]);
+        assert_ne!(nodes[3].position, before[3]);
+
+        // once released, a node moves again
+        fs.set_fixed(&[0], false);
+        fs.step();
+        assert_ne!(fs.get_nodes()[0].position, before[0] + delta);
+        assert_eq!(fs.get_nodes()[1].position, before[1] + delta);
     }
 
     #[test]
-    pub fn test_total_force_n1_exerts_on_n2() {
+    pub fn test_repulsion_cutoff() {
+        // a close pair and a far node, none of them connected
+        let nodes = vec![
+            Node::new().position(Vector2D::from_xy(0.0, 0.0)).build(),
+            Node::new().position(Vector2D::from_xy(1.0, 0.0)).build(),
+            Node::new().position(Vector2D::from_xy(10.0, 0.0)).build(),
+        ];
+        let uncut = ForceSimulation::new_with_params(nodes.clone(), vec![], SimParams::default());
+        let cut = ForceSimulation::new_with_params(
+            nodes,
+            vec![],
+            SimParams {
+                repulsion_cutoff: Some(2.0),
+                ..SimParams::default()
+            },
+        );
+
+        // within the cutoff the pair repels as before, and beyond it the far node feels nothing
+        let pairwise_uncut = uncut.calculate_forces();
+        let pairwise_cut = cut.calculate_forces();
+        assert_eq!(pairwise_cut[0][1], pairwise_uncut[0][1]);
+        assert_eq!(pairwise_cut[1][0], pairwise_uncut[1][0]);
+        assert!(pairwise_uncut[0][2].magnitude() > 0.0);
+        assert_eq!(pairwise_cut[0][2], Vector2D::from_xy(0.0, 0.0));
+        assert_eq!(pairwise_cut[1][2], Vector2D::from_xy(0.0, 0.0));
+        assert_eq!(cut.compute_forces()[2], Vector2D::from_xy(0.0, 0.0));
+    }
+
+    #[test]
+    pub fn test_repulsion_cutoff_large() {
+        // a cutoff far larger than the layout leaves it practically unchanged
         let (nodes, edges) = setup();
-        let weight = edges[1].weight;
-        let mut fs = get_force_simulation();
+        let params = SimParams {
+            time_step: 0.01,
+            ..SimParams::default()
+        };
+        let mut uncut = ForceSimulation::new_with_params(nodes.clone(), edges.clone(), params);
+        let mut cut = ForceSimulation::new_with_params(
+            nodes,
+            edges,
+            SimParams {
+                repulsion_cutoff: Some(100.0),
+                ..params
+            },
+        );
+        uncut.step_n(50);
+        cut.step_n(50);
+        for (a, b) in uncut.get_nodes().iter().zip(cut.get_nodes()) {
+            assert_vec_approx!(a.position, b.position, 1e-9);
+        }
+    }
 
-        let mut n1 = &nodes[0];
-        let mut n2 = &nodes[2];
-        let repulsive_force: Vector2D<f64> = fs
-            .repulsive_force_n1_exerts_on_n2(
-                n1.position.distance(&n2.position),
-                n1.mass,
-                n2.mass,
-                n1.position.relative_to(&n2.position).angle(),
-            )
-            .round(5);
+    #[test]
+    pub fn test_edge_crossings() {
+        // a unit square with both diagonals, and a fifth node above it joined to a corner
+        let nodes: Vec<Node> = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0), (0.5, 2.0)]
+            .iter()
+            .enumerate()
+            .map(|(i, &(x, y))| Node::new().id(i).position(Vector2D::from_xy(x, y)).build())
+            .collect();
+        let mut edges = vec![
+            Edge::new(0, 1, 1.0),
+            Edge::new(1, 2, 1.0),
+            Edge::new(2, 3, 1.0),
+            Edge::new(3, 0, 1.0),
+            Edge::new(0, 2, 1.0),
+            Edge::new(1, 3, 1.0),
+        ];
+        let fs = ForceSimulation::new(nodes.clone(), edges.clone(), 1.0, 1.0, 1.0);
 
-        println!("repulsive_force: {:?}", repulsive_force);
+        // only the diagonals cross, since the sides share their corners
+        assert_eq!(fs.edge_crossings(), 1);
 
-        let attractive_force = fs
-            .attractive_force_n1_exerts_on_n2(
-                n1.position.distance(&n2.position),
-                weight,
-                n1.position.relative_to(&n2.position).angle(),
-            )
-            .round(5);
+        // the new edge crosses the top side and the diagonal from 1 to 3, but not the diagonal
+        // from 0 that it shares a node with
+        edges.push(Edge::new(0, 4, 1.0));
+        let fs = ForceSimulation::new(nodes, edges, 1.0, 1.0, 1.0);
+        assert_eq!(fs.edge_crossings(), 3);
+    }
 
-        println!("attractive_force: {:?}", attractive_force);
+    #[test]
+    pub fn test_overlap_count() {
+        let unit_circles = |positions: &[(f64, f64)]| {
+            let nodes = positions
+                .iter()
+                .map(|&(x, y)| Node::new().position(Vector2D::from_xy(x, y)).build())
+                .collect();
+            ForceSimulation::new(nodes, vec![], 1.0, 1.0, 1.0)
+        };
 
-        let expected = attractive_force - repulsive_force;
+        // well separated, or only touching
+        let apart = unit_circles(&[(0.0, 0.0), (2.0, 0.0), (10.0, 10.0)]);
+        assert_eq!(apart.overlap_count(), 0);
+        assert_eq!(apart.total_overlap_area(), 0.0);
 
-        println!("expected: {:?}", expected);
+        // coincident circles overlap completely, and circles a radius apart form a lens of area
+        // 2 * pi / 3 - sqrt(3) / 2
+        let overlapping = unit_circles(&[(0.0, 0.0), (0.0, 0.0), (1.0, 0.0), (10.0, 0.0)]);
+        assert_eq!(overlapping.overlap_count(), 3);
+        let lens = 2.0 * PI / 3.0 - 3.0_f64.sqrt() / 2.0;
+        assert!(approx_equal(
+            overlapping.total_overlap_area(),
+            PI + 2.0 * lens,
+            1e-12
+        ));
 
-        let actual = fs.total_force_n1_exerts_on_n2(n1, n2, weight).round(5);
-        assert_eq!(actual, expected);
+        // a small circle inside a large one overlaps by its whole area
+        let nodes = vec![
+            Node::new().radius(3.0).build(),
+            Node::new().radius(0.5).build(),
+        ];
+        let nested = ForceSimulation::new(nodes, vec![], 1.0, 1.0, 1.0);
+        assert_eq!(nested.overlap_count(), 1);
+        assert!(approx_equal(nested.total_overlap_area(), PI * 0.25, 1e-12));
     }
 
     #[test]
-    pub fn test_acceleration_from_force_n1_exerts_on_n2() {
+    pub fn test_export_import_positions() {
         let (nodes, edges) = setup();
-        let weight = edges[1].weight;
+        let mut fs = ForceSimulation::new(nodes.clone(), edges.clone(), 0.1, 1.0, 1.0);
+        fs.step_n(10);
+        let layout = fs.export_positions();
+        assert_eq!(layout.len(), 3);
+        assert_eq!(layout[1].0, 2);
+
+        // restoring onto a fresh simulation reproduces the layout
+        let mut restored = ForceSimulation::new(nodes.clone(), edges.clone(), 0.1, 1.0, 1.0);
+        restored.import_positions(&layout);
+        assert_eq!(restored.export_positions(), layout);
+        assert_eq!(restored.positions[1], fs.get_nodes()[1].position);
+
+        // missing ids keep their position, and unknown ids are ignored
+        let mut partial = ForceSimulation::new(nodes, edges, 0.1, 1.0, 1.0);
+        partial.import_positions(&[(1, 5.0, 6.0), (99, 7.0, 8.0)]);
+        let positions = partial.export_positions();
+        assert_eq!(positions[0], (1, 5.0, 6.0));
+        assert_eq!(positions[1], (2, 1.0, 0.0));
+        assert_eq!(positions[2], (3, 0.0, 1.0));
+    }
+
+    #[test]
+    pub fn test_layout_quality() {
+        // a four-cycle drawn as a square of side `side`, with nodes 1 and 2 swapped if `twisted`
+        let square = |side: f64, twisted: bool| {
+            let mut corners = vec![(0.0, 0.0), (side, 0.0), (side, side), (0.0, side)];
+            if twisted {
+                corners.swap(1, 2);
+            }
+            let nodes = corners
+                .into_iter()
+                .enumerate()
+                .map(|(i, (x, y))| Node::at(i, Vector2D::from_xy(x, y)))
+                .collect();
+            let edges = (0..4).map(|i| Edge::between(i, (i + 1) % 4, 1.0)).collect();
+            ForceSimulation::new(nodes, edges, 0.01, 1.0, 1.0).layout_quality()
+        };
+
+        let good = square(3.0, false);
+        assert_eq!(good.edge_crossings, 0);
+        assert_eq!(good.node_overlaps, 0);
+        assert!(approx_equal(good.edge_length_cv, 0.0, 1e-12));
+        assert!(approx_equal(good.min_node_distance, 3.0, 1e-12));
+
+        // the twisted drawing has one crossing, and at side 1.5 the unit circles overlap
+        let bad = square(1.5, true);
+        assert!(bad.edge_crossings > good.edge_crossings);
+        assert!(bad.node_overlaps > good.node_overlaps);
+        assert!(bad.edge_length_cv > good.edge_length_cv);
+        assert!(bad.min_node_distance < good.min_node_distance);
+
+        let empty = ForceSimulation::new(vec![], vec![], 0.01, 1.0, 1.0).layout_quality();
+        assert_eq!(empty.edge_length_cv, 0.0);
+        assert_eq!(empty.min_node_distance, f64::INFINITY);
+    }
+
+    #[test]
+    pub fn test_simulation_state() {
         let mut fs = get_force_simulation();
+        fs.time_step = 0.01;
+        let state = fs.state(1e-3);
+        assert_eq!(state.step_count, 0);
+        assert!(!state.settled);
+        assert_eq!(fs.last_report(), None);
 
-        let mut n1 = &nodes[0];
-        let mut n2 = &nodes[2];
-        let force = fs.total_force_n1_exerts_on_n2(n1, n2, weight);
+        fs.step_n(7);
+        let state = fs.state(1e-3);
+        assert_eq!(state.step_count, 7);
+        assert_eq!(state.settled, fs.is_settled(1e-3));
+        assert_eq!(state.kinetic_energy, fs.kinetic_energy());
+        assert_eq!(state.positions, fs.export_positions());
 
-        println!("net force: {:?}", force);
+        // settled is judged against the tolerance given
+        let moved = fs.last_report().unwrap().max_displacement;
+        assert!(moved > 0.0);
+        assert!(fs.state(2.0 * moved).settled);
+        assert!(!fs.state(moved).settled);
 
-        let expected = force / n2.mass;
+        let json = serde_json::to_string(&state).unwrap();
+        assert!(json.contains("\"step_count\":7"));
+        let restored: SimulationState = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.step_count, state.step_count);
+        assert_eq!(restored.settled, state.settled);
+        assert_eq!(restored.positions.len(), 3);
+    }
 
-        println!("expected: {:?}", expected);
+    #[test]
+    pub fn test_relax_around() {
+        // a path 0 - 1 - ... - 7 along the x axis, with a new node 8 just added next to node 0
+        let mut nodes: Vec<Node> = (0..8)
+            .map(|i| Node::at(i, Vector2D::from_xy(i as f64, 0.0)))
+            .collect();
+        nodes.push(Node::at(8, Vector2D::from_xy(0.0, 0.1)));
+        let mut edges: Vec<Edge> = (0..7).map(|i| Edge::between(i, i + 1, 1.0)).collect();
+        edges.push(Edge::between(8, 0, 1.0));
+        let mut fs = ForceSimulation::new(nodes, edges, 0.01, 1.0, 1.0);
+        let before = fs.get_nodes().clone();
 
-        let actual = fs.acceleration_from_force_n1_exerts_on_n2(n1, n2, weight);
-        assert_eq!(actual.round(5), expected.round(5));
+        fs.relax_around(8, 2, 20);
+        assert_eq!(fs.steps_taken, 20);
+
+        // the new node and its neighbors within two hops moved, and nothing further out did
+        let moved = |i: usize| fs.get_nodes()[i].position != before[i].position;
+        assert!(moved(8));
+        assert!(moved(0));
+        assert!(moved(1));
+        for i in 2..8 {
+            assert!(!moved(i), "node {} moved", i);
+        }
+        assert!(fs.get_nodes().iter().all(|node| !node.fixed));
     }
 
     #[test]
-    pub fn test_chg_in_velocity_from_force_n1_exerts_on_n2() {
-        let time_step = 0.25;
+    pub fn test_step_reuses_scratch_buffers() {
+        for integrator in [Integrator::Euler, Integrator::VelocityVerlet] {
+            let (nodes, edges) = fully_connected(5);
+            let params = SimParams {
+                integrator,
+                ..SimParams::default()
+            };
+            let mut reused = ForceSimulation::new_with_params(nodes, edges, params);
+            reused.set_sleep_threshold(1e-9, 1000);
+            reused.step();
+            let mut allocating = reused.clone();
+            let pairwise = reused.scratch.pairwise.as_ptr();
+            let capacity = reused.scratch.pairwise[0].capacity();
+            let previous_positions = reused.scratch.previous_positions.as_ptr();
+            let moving = reused.scratch.moving.as_ptr();
+            for _ in 0..50 {
+                reused.step();
+
+                // computing fresh forces and integrating them is the same as a step
+                let forces = allocating.compute_forces();
+                allocating.integrate(&forces);
+                assert_eq!(reused.max_position_diff(&allocating), 0.0);
+                assert_eq!(reused.last_forces(), &forces);
+            }
+
+            // the buffers were reused rather than reallocated
+            assert_eq!(reused.scratch.pairwise.as_ptr(), pairwise);
+            assert_eq!(reused.scratch.pairwise[0].capacity(), capacity);
+            assert_eq!(
+                reused.scratch.previous_positions.as_ptr(),
+                previous_positions
+            );
+            assert_eq!(reused.scratch.moving.as_ptr(), moving);
+        }
+    }
+
+    #[test]
+    pub fn test_max_net_force() {
+        // springs of rest length 1 and no repulsion, so an equilateral triangle of side 1 is at
+        // equilibrium
+        let triangle = |side: f64| {
+            let nodes = (0..3)
+                .map(|i| {
+                    let angle = i as f64 * 2.0 * PI / 3.0;
+                    Node::at(i, Vector2D::from_rtheta(side / 3f64.sqrt(), angle))
+                })
+                .collect();
+            let edges = vec![
+                Edge::new(0, 1, 1.0),
+                Edge::new(0, 2, 1.0),
+                Edge::new(1, 2, 1.0),
+            ];
+            let params = SimParams {
+                time_step: 0.1,
+                repulsion_constant: 0.0,
+                damping: 0.2,
+                weight_semantics: WeightSemantics::Distance,
+                ..SimParams::default()
+            };
+            ForceSimulation::new_with_params(nodes, edges, params)
+        };
+        assert!(triangle(1.0).max_net_force() < 1e-12);
+
+        let mut stretched = triangle(2.0);
+        assert!(stretched.max_net_force() > 0.1);
+        let steps = stretched.run_until_balanced(1e-6, 1000);
+        assert!(steps > 0 && steps < 1000);
+        assert!(stretched.max_net_force() < 1e-6);
+        assert_eq!(stretched.run_until_balanced(1e-6, 1000), 0);
+    }
+
+    #[test]
+    pub fn test_step_into() {
         let (nodes, edges) = setup();
-        let weight = edges[1].weight;
-        let mut fs = get_force_simulation();
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, SimParams::default());
+        let mut reference = fs.clone();
 
-        let mut n1 = &nodes[0];
-        let mut n2 = &nodes[2];
-        let acceleration = fs.acceleration_from_force_n1_exerts_on_n2(n1, n2, weight);
+        let mut out = vec![f64::NAN; 7];
+        let report = fs.step_into(&mut out).unwrap();
+        assert_eq!(report, reference.step());
+        let expected: Vec<f64> = reference
+            .get_nodes()
+            .iter()
+            .flat_map(|node| [node.position.x, node.position.y])
+            .collect();
+        assert_eq!(out[..6], expected[..]);
+        assert!(out[6].is_nan());
 
-        println!("acceleration: {:?}", acceleration);
+        // a buffer too small for every position is rejected before stepping
+        let mut short = [0.0; 5];
+        assert!(matches!(
+            fs.step_into(&mut short),
+            Err(Error::BufferTooSmall(6, 5))
+        ));
+        assert_eq!(short, [0.0; 5]);
+        assert_eq!(fs.max_position_diff(&reference), 0.0);
+    }
 
-        let v0 = n2.velocity;
-        let delta_v = acceleration * time_step;
+    #[test]
+    pub fn test_sleeping_nodes() {
+        // settle a layout almost completely
+        let (nodes, edges) = fully_connected(6);
+        let params = SimParams {
+            time_step: 0.05,
+            damping: 0.1,
+            gravity: 1.0,
+            ..SimParams::default()
+        };
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, params);
+        fs.step_n(300);
 
-        println!("expected delta v: {:?}", delta_v);
+        fs.set_sleep_threshold(1e-2, 3);
+        assert_eq!(fs.awake_count(), 6);
+        let mut awake = vec![];
+        for _ in 0..10 {
+            fs.step();
+            awake.push(fs.awake_count());
+        }
+        assert!(awake.windows(2).all(|pair| pair[1] <= pair[0]));
+        assert_eq!(*awake.last().unwrap(), 0);
 
-        let actual = fs.chg_in_velocity_from_force_n1_exerts_on_n2(n1, n2, weight, time_step);
-        assert_eq!(actual.round(5), delta_v.round(5));
+        // sleeping nodes do not move
+        let before = fs.clone();
+        fs.step();
+        assert_eq!(fs.max_position_diff(&before), 0.0);
+
+        // kicking one node wakes it, and its movement wakes its neighbors
+        fs.set_velocity(0, Vector2D::from_xy(1.0, 0.0));
+        assert_eq!(fs.awake_count(), 1);
+        fs.step();
+        assert_eq!(fs.awake_count(), 6);
+
+        fs.disable_sleep();
+        assert_eq!(fs.awake_count(), 6);
     }
 
     #[test]
-    pub fn test_chg_in_position_from_force_n1_exerts_on_n2() {
-        let time_step = 5.0;
+    pub fn test_steps_iterator() {
+        let mut fs = get_force_simulation();
+        fs.time_step = 0.01;
+        assert_eq!(fs.steps().take(5).count(), 5);
+        assert_eq!(fs.steps_taken, 5);
+
+        // run until settled, with a cap on the steps in case it never does
+        let epsilon = 1e-4;
         let (nodes, edges) = setup();
-        let weight = edges[1].weight;
+        let params = SimParams {
+            time_step: 0.05,
+            damping: 0.1,
+            gravity: 1.0,
+            ..SimParams::default()
+        };
+        let mut fs = ForceSimulation::new_with_params(nodes.clone(), edges.clone(), params);
+        let last = fs
+            .steps()
+            .take(10_000)
+            .find(|report| report.max_displacement <= epsilon)
+            .unwrap();
+        assert!(last.max_displacement <= epsilon);
+        assert!(fs.steps_taken < 10_000);
+        let settled_at = fs.steps_taken;
+
+        // take_while stops just before the first settled step
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, params);
+        let reports: Vec<StepReport> = fs
+            .steps()
+            .take_while(|report| report.max_displacement > epsilon)
+            .collect();
+        assert_eq!(reports.len(), settled_at - 1);
+        assert!(reports
+            .iter()
+            .all(|report| report.max_displacement > epsilon));
+    }
+
+    #[test]
+    pub fn test_run_until() {
+        let (nodes, edges) = fully_connected(6);
+        let mut fs = ForceSimulation::new(nodes.clone(), edges.clone(), 0.01, 1.0, 1.0);
+
+        // a deadline in the past takes no steps
+        let before = fs.get_nodes().clone();
+        assert_eq!(fs.run_until(Instant::now(), 0.0), 0);
+        assert_eq!(fs.get_nodes()[0].position, before[0].position);
+
+        // a short deadline with an unreachable tolerance returns on time
+        let budget = std::time::Duration::from_millis(20);
+        let start = Instant::now();
+        let steps = fs.run_until(start + budget, 0.0);
+        assert!(steps > 0);
+        assert!(start.elapsed() < budget * 10);
+        assert_eq!(fs.steps_taken, steps);
+
+        // a generous tolerance counts as settled after a single step
+        let mut fs = ForceSimulation::new(nodes, edges, 0.01, 1.0, 1.0);
+        let deadline = Instant::now() + std::time::Duration::from_secs(60);
+        assert_eq!(fs.run_until(deadline, f64::INFINITY), 1);
+    }
+
+    #[test]
+    pub fn test_diff() {
+        let fs = get_force_simulation();
+        let copy = fs.clone();
+        assert_eq!(fs.diff(&copy), vec![(1, 0.0), (2, 0.0), (3, 0.0)]);
+        assert_eq!(fs.max_position_diff(&copy), 0.0);
+
+        let mut stepped = fs.clone();
+        stepped.step();
+        let diff = fs.diff(&stepped);
+        assert_eq!(diff.len(), 3);
+        for (id, distance) in diff.iter() {
+            let moved = fs
+                .position_of(*id)
+                .unwrap()
+                .distance(&stepped.position_of(*id).unwrap());
+            assert_eq!(*distance, moved);
+        }
+        assert!(fs.max_position_diff(&stepped) > 0.0);
+
+        // nodes are matched by id, not index, and unmatched ids are skipped
+        let mut shuffled = fs.clone();
+        shuffled.shuffle_ids(7);
+        shuffled.nodes[0].id = 99;
+        let diff = fs.diff(&shuffled);
+        assert_eq!(diff.len(), 2);
+        for (id, distance) in diff.iter() {
+            let moved = fs
+                .position_of(*id)
+                .unwrap()
+                .distance(&shuffled.position_of(*id).unwrap());
+            assert_eq!(*distance, moved);
+        }
+    }
+
+    #[test]
+    pub fn test_no_repel_group() {
+        // two identical pairs, joined by identical edges, differing only in their no-repel groups
+        let pair = |groups: [usize; 2]| {
+            let nodes = (0..2)
+                .map(|i| {
+                    Node::new()
+                        .id(i)
+                        .position(Vector2D::from_xy(i as f64, 0.0))
+                        .no_repel_group(groups[i])
+                        .build()
+                })
+                .collect();
+            let mut fs =
+                ForceSimulation::new(nodes, vec![Edge::between(0, 1, 1.0)], 0.01, 1.0, 1.0);
+            fs.step_n(50);
+            fs.get_nodes()[0]
+                .position
+                .distance(&fs.get_nodes()[1].position)
+        };
+        assert!(pair([0, 0]) < pair([0, 1]));
+
+        // nodes in the same group feel only the edge between them
+        let nodes = vec![
+            Node::new().id(0).no_repel_group(5).build(),
+            Node::new()
+                .id(1)
+                .position(Vector2D::from_xy(4.0, 1.0))
+                .no_repel_group(5)
+                .build(),
+        ];
+        let fs = ForceSimulation::new(nodes, vec![], 0.01, 1.0, 1.0);
+        assert_eq!(fs.compute_forces()[1], Vector2D::from_xy(0.0, 0.0));
+        assert_eq!(fs.system_energy(), 0.0);
+    }
+
+    #[test]
+    pub fn test_ensure_distinct_positions() {
+        // every default node starts at the same point
+        let nodes: Vec<Node> = (0..6).map(|i| Node::new().id(i).build()).collect();
+        let edges = (0..5).map(|i| Edge::between(i, i + 1, 1.0)).collect();
+        let mut fs = ForceSimulation::new(nodes, edges, 0.01, 1.0, 1.0);
+        let start = fs.get_nodes()[0].position;
+
+        assert_eq!(fs.ensure_distinct_positions(0.0, 3), 0);
+        assert_eq!(fs.ensure_distinct_positions(0.5, 3), 5);
+        assert_eq!(fs.ensure_distinct_positions(0.5, 3), 0);
+        assert_eq!(fs.get_nodes()[0].position, start);
+        for (i, j) in fs.node_pairs() {
+            assert_ne!(fs.get_nodes()[i].position, fs.get_nodes()[j].position);
+        }
+        for node in fs.get_nodes() {
+            assert!(node.position.distance(&start) <= 0.5 * 2.0_f64.sqrt() + 1e-12);
+        }
+
+        fs.step_n(20);
+        assert!(fs.get_nodes().iter().all(|node| node.position.is_finite()));
+    }
+
+    #[test]
+    pub fn test_non_finite_jitter() {
+        let nodes: Vec<Node> = (0..3).map(|i| Node::new().id(i).build()).collect();
+        let mut fs = ForceSimulation::new(nodes, vec![], 0.01, 1.0, 1.0);
+        let original = fs.get_nodes().clone();
+        for jitter in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert_eq!(fs.ensure_distinct_positions(jitter, 3), 0);
+            fs.add_jitter(jitter, 3);
+            fs.add_jitter_from_state(jitter);
+        }
+        for (node, original) in fs.get_nodes().iter().zip(&original) {
+            assert_eq!(node.position, original.position);
+        }
+    }
+
+    #[test]
+    pub fn test_has_coincident_positions() {
+        let nodes: Vec<Node> = (0..3).map(|i| Node::new().id(i).build()).collect();
+        let mut fs = ForceSimulation::new(nodes.clone(), vec![], 0.01, 1.0, 1.0);
+        assert!(fs.has_coincident_positions());
+        fs.ensure_distinct_positions(0.5, 3);
+        assert!(!fs.has_coincident_positions());
+        assert!(!get_force_simulation().has_coincident_positions());
+
+        let params = SimParams {
+            coincident_jitter: Some(0.5),
+            ..SimParams::default()
+        };
+        let fs = ForceSimulation::new_with_params(nodes, vec![], params);
+        assert!(!fs.has_coincident_positions());
+    }
+
+    #[test]
+    pub fn test_jitter_leaves_fixed_nodes() {
         let mut fs = get_force_simulation();
+        fs.set_fixed(&[1], true);
+        let original = fs.get_nodes().clone();
+        fs.add_jitter(0.5, 1);
+        fs.add_jitter_from_state(0.5);
+        assert_eq!(fs.get_nodes()[1].position, original[1].position);
+        assert_ne!(fs.get_nodes()[0].position, original[0].position);
+        assert_ne!(fs.get_nodes()[2].position, original[2].position);
 
-        let mut n1 = &nodes[0];
-        let mut n2 = &nodes[2];
-        let acceleration = fs.acceleration_from_force_n1_exerts_on_n2(n1, n2, weight);
+        // a fixed node keeps its position even when a movable node got there first
+        let nodes: Vec<Node> = (0..3).map(|i| Node::new().id(i).build()).collect();
+        let mut fs = ForceSimulation::new(nodes, vec![], 0.01, 1.0, 1.0);
+        let start = fs.get_nodes()[0].position;
+        fs.set_fixed(&[2], true);
+        assert_eq!(fs.ensure_distinct_positions(0.5, 3), 2);
+        assert_eq!(fs.get_nodes()[2].position, start);
+        assert_ne!(fs.get_nodes()[0].position, start);
+        assert_ne!(fs.get_nodes()[1].position, start);
+    }
 
-        println!("acceleration: {:?}", acceleration.round(3));
+    #[test]
+    pub fn test_empty_and_single_node() {
+        let lone = Node::new().position(Vector2D::from_xy(2.0, 3.0)).build();
+        for nodes in [vec![], vec![lone]] {
+            let n_nodes = nodes.len();
+            let mut fs = ForceSimulation::new(nodes, vec![], 0.1, 1.0, 1.0);
+            let before = fs.get_nodes().clone();
 
-        let p0 = n2.position;
-        let v0 = n2.velocity;
+            let report = fs.step();
+            assert_eq!(report.max_displacement, 0.0);
+            assert_eq!(report.total_movement, 0.0);
+            fs.step_n(10);
+            for (node, original) in fs.get_nodes().iter().zip(before.iter()) {
+                assert_eq!(node.position, original.position);
+            }
 
-        println!("p0: {:?}", p0.round(3));
-        println!("v0: {:?}", v0.round(3));
+            let json = fs.to_json().unwrap();
+            assert!(serde_json::from_str::<serde_json::Value>(&json).is_ok());
+            assert_eq!(
+                ForceSimulation::from_json(&json).unwrap().n_nodes(),
+                n_nodes
+            );
+        }
+    }
 
-        let pf = p0 + v0 * time_step + acceleration * time_step.powi(2) / 2.0;
+    #[test]
+    pub fn test_min_distance() {
+        let at = |x: f64| Node::new().position(Vector2D::from_xy(x, 0.0)).build();
+        let mut fs = ForceSimulation::new(vec![], vec![], 1.0, 1.0, 1.0);
+        fs.set_min_distance(0.1);
 
-        println!("expected pf: {:?}", pf.round(3));
+        // coincident nodes feel the force of nodes exactly min_distance apart. A weight of 2 keeps
+        // the attraction from cancelling the repulsion exactly.
+        let coincident = fs.total_force_n1_exerts_on_n2(&at(0.0), &at(0.0), 2.0);
+        let at_floor = fs.total_force_n1_exerts_on_n2(&at(0.0), &at(0.1), 2.0);
+        assert!(approx_equal(
+            coincident.magnitude(),
+            at_floor.magnitude(),
+            1e-12
+        ));
+        assert!(coincident.magnitude().is_finite());
 
-        let delta_p = pf - p0;
+        // the default floor is far smaller, so the force is far larger
+        let default = ForceSimulation::new(vec![], vec![], 1.0, 1.0, 1.0);
+        let unfloored = default.total_force_n1_exerts_on_n2(&at(0.0), &at(0.0), 2.0);
+        assert!(unfloored.magnitude() > 1e6 * coincident.magnitude());
+    }
 
-        println!("expected delta p: {:?}", delta_p.round(3));
+    #[test]
+    pub fn test_force_sign_convention() {
+        // the default linear spring against inverse-square repulsion, with both masses equal to
+        // the edge weight of 1, balances at a distance of 1
+        let params = SimParams::default();
+        let distance_after_step = |distance: f64| {
+            let nodes = vec![
+                Node::new().position(Vector2D::from_xy(0.0, 0.0)).build(),
+                Node::new()
+                    .position(Vector2D::from_xy(distance, 0.0))
+                    .build(),
+            ];
+            let mut fs =
+                ForceSimulation::new_with_params(nodes, vec![Edge::new(0, 1, 1.0)], params);
+            fs.step();
+            let nodes = fs.get_nodes();
+            nodes[0].position.distance(&nodes[1].position)
+        };
 
-        let actual = fs.chg_in_position_from_force_n1_exerts_on_n2(n1, n2, weight, time_step);
-        assert_eq!(actual.round(5), delta_p.round(5));
+        // closer than equilibrium the net force repels, and farther it attracts
+        assert!(distance_after_step(0.5) > 0.5);
+        assert!(distance_after_step(2.0) < 2.0);
+        assert!(approx_equal(distance_after_step(1.0), 1.0, 1e-12));
+    }
+
+    #[test]
+    pub fn test_degree_scaled_repulsion() {
+        // two connected hubs, each with six leaves around it
+        let mut nodes = vec![
+            Node::new().position(Vector2D::from_xy(-1.0, 0.0)).build(),
+            Node::new().position(Vector2D::from_xy(1.0, 0.0)).build(),
+        ];
+        let mut edges = vec![Edge::new(0, 1, 1.0)];
+        for hub in 0..2 {
+            for k in 0..6 {
+                let offset = Vector2D::from_rtheta(0.5, PI * k as f64 / 3.0);
+                nodes.push(Node::new().position(nodes[hub].position + offset).build());
+                edges.push(Edge::new(hub, nodes.len() - 1, 1.0));
+            }
+        }
+
+        let hub_distance = |degree_scaled_repulsion: bool| {
+            let params = SimParams {
+                time_step: 0.01,
+                damping: 0.1,
+                degree_scaled_repulsion,
+                ..SimParams::default()
+            };
+            let mut fs = ForceSimulation::new_with_params(nodes.clone(), edges.clone(), params);
+            fs.step_n(500);
+            let nodes = fs.get_nodes();
+            nodes[0].position.distance(&nodes[1].position)
+        };
+        assert!(hub_distance(true) > hub_distance(false));
+    }
+
+    #[test]
+    pub fn test_hidden_nodes() {
+        let (nodes, edges) = setup();
+        let mut shown = ForceSimulation::new(nodes.clone(), edges.clone(), 0.1, 1.0, 1.0);
+
+        let mut hidden_nodes = nodes;
+        hidden_nodes[2].visible = false;
+        let mut hidden = ForceSimulation::new(hidden_nodes, edges.clone(), 0.1, 1.0, 1.0);
+
+        // the hidden node is left out of render output
+        let ids: Vec<usize> = hidden.visible_nodes().map(|node| node.id).collect();
+        assert_eq!(ids, vec![1, 2]);
+        assert_eq!(shown.visible_nodes().count(), 3);
+
+        // but it moves its neighbors exactly as if it were shown
+        shown.step_n(5);
+        hidden.step_n(5);
+        for (a, b) in shown.get_nodes().iter().zip(hidden.get_nodes()) {
+            assert_eq!(a.position, b.position);
+        }
+
+        // which differs from leaving it out of the simulation altogether
+        let (nodes, _) = setup();
+        let mut without =
+            ForceSimulation::new(nodes[..2].to_vec(), vec![edges[0].clone()], 0.1, 1.0, 1.0);
+        without.step_n(5);
+        assert_ne!(
+            without.get_nodes()[0].position,
+            hidden.get_nodes()[0].position
+        );
+    }
+
+    #[test]
+    pub fn test_edge_repulsion_reduces_crossings() {
+        // rings of 12 nodes scattered at random, whose drawings start out tangled, given a fixed
+        // budget of steps to untangle
+        let crossings = |edge_repulsion: Option<EdgeRepulsion>| {
+            (0..10)
+                .map(|seed| {
+                    let nodes: Vec<Node> = (0..12).map(|id| Node::new().id(id).build()).collect();
+                    let edges: Vec<Edge> =
+                        (0..12).map(|i| Edge::new(i, (i + 1) % 12, 1.0)).collect();
+                    let params = SimParams {
+                        time_step: 0.05,
+                        damping: 0.2,
+                        gravity: 0.05,
+                        edge_repulsion,
+                        ..SimParams::default()
+                    };
+                    let mut rng = StdRng::seed_from_u64(seed);
+                    let mut fs = ForceSimulation::new_with_random_positions(
+                        nodes, edges, params, 5.0, &mut rng,
+                    );
+                    fs.step_n(200);
+                    fs.edge_crossings()
+                })
+                .sum::<usize>()
+        };
+        let without = crossings(None);
+        let with = crossings(Some(EdgeRepulsion {
+            strength: 1.0,
+            range: 2.0,
+        }));
+        assert!(
+            with < without,
+            "{} crossings with, {} without",
+            with,
+            without
+        );
+    }
+
+    #[test]
+    pub fn test_injected_rng() {
+        let build = |seed: u64| {
+            let (nodes, edges) = setup();
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut fs = ForceSimulation::new_with_random_positions(
+                nodes,
+                edges,
+                SimParams::default(),
+                10.0,
+                &mut rng,
+            );
+            fs.add_jitter_with_rng(0.5, &mut rng);
+            fs.add_jitter_with_rng(0.5, &mut rng);
+            fs.export_positions()
+        };
+
+        // the same seed gives the same layout and jitter, and a different seed does not
+        assert_eq!(build(7), build(7));
+        assert_ne!(build(7), build(8));
+        assert!(build(7)
+            .iter()
+            .all(|&(_, x, y)| x.abs() <= 11.0 && y.abs() <= 11.0));
+    }
+
+    #[test]
+    pub fn test_position_delta() {
+        let (nodes, edges) = setup();
+        let params = SimParams {
+            time_step: 0.01,
+            damping: 0.2,
+            ..SimParams::default()
+        };
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, params);
+
+        // the first call sends everything
+        assert_eq!(fs.position_delta(1e-3).len(), 3);
+        assert!(fs.position_delta(1e-3).is_empty());
+
+        // while the layout is moving, the moved nodes are sent
+        fs.step();
+        let moving = fs.position_delta(0.0);
+        assert_eq!(moving.len(), 3);
+        let position = fs.get_nodes()[1].position;
+        assert_eq!(moving[1], (1, position.x, position.y));
+
+        // once it has settled, little or nothing is
+        fs.step_n(2000);
+        fs.position_delta(1e-3);
+        fs.step();
+        assert!(fs.position_delta(1e-3).is_empty());
+    }
+
+    #[test]
+    pub fn test_add_obstacle() {
+        let (nodes, edges) = setup();
+        let params = SimParams {
+            time_step: 0.05,
+            damping: 0.5,
+            ..SimParams::default()
+        };
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, params);
+        let center = Vector2D::from_xy(0.2, 0.2);
+        let radius = 3.0;
+        fs.add_obstacle(center, radius, 500.0);
+        assert!(fs
+            .get_nodes()
+            .iter()
+            .all(|node| node.position.distance(&center) < radius));
+
+        // every node starts inside the obstacle and is pushed out
+        fs.step_n(200);
+        assert!(fs
+            .get_nodes()
+            .iter()
+            .all(|node| node.position.distance(&center) > radius * 0.9));
+
+        // a node outside the obstacle, or any node once it is cleared, feels nothing from it
+        let outside = Node::new().position(Vector2D::from_xy(10.0, 0.0)).build();
+        let mut fs = ForceSimulation::new(vec![outside], vec![], 1.0, 1.0, 1.0);
+        fs.add_obstacle(center, radius, 50.0);
+        assert_eq!(fs.external_acceleration(0), Vector2D::from_xy(0.0, 0.0));
+        fs.import_positions(&[(0, 1.0, 0.0)]);
+        assert!(fs.external_acceleration(0).x > 0.0);
+        fs.clear_obstacles();
+        assert_eq!(fs.external_acceleration(0), Vector2D::from_xy(0.0, 0.0));
+    }
+
+    #[test]
+    pub fn test_apply_affine() {
+        let (nodes, edges) = setup();
+        let mut fs = ForceSimulation::new(nodes, edges, 1.0, 1.0, 1.0);
+        let before: Vec<Vector2D<f64>> = fs.get_nodes().iter().map(|n| n.position).collect();
+
+        // the identity leaves every node in place
+        fs.apply_affine(1.0, 0.0, Vector2D::from_xy(0.0, 0.0));
+        for (node, position) in fs.get_nodes().iter().zip(before.iter()) {
+            assert_vec_approx!(node.position, *position, 1e-12);
+        }
+
+        // (0, 0), (1, 0) and (0, 1) are scaled by 2 and then moved by (3, -1)
+        fs.apply_affine(2.0, 0.0, Vector2D::from_xy(3.0, -1.0));
+        let nodes = fs.get_nodes();
+        assert_vec_approx!(nodes[0].position, Vector2D::from_xy(3.0, -1.0), 1e-12);
+        assert_vec_approx!(nodes[1].position, Vector2D::from_xy(5.0, -1.0), 1e-12);
+        assert_vec_approx!(nodes[2].position, Vector2D::from_xy(3.0, 1.0), 1e-12);
+        assert_eq!(fs.positions[1], nodes[1].position);
+
+        // scaling happens before the rotation, and the translation last
+        let mut fs = ForceSimulation::new(
+            vec![Node::new().position(Vector2D::from_xy(1.0, 0.0)).build()],
+            vec![],
+            1.0,
+            1.0,
+            1.0,
+        );
+        fs.apply_affine(3.0, PI / 2.0, Vector2D::from_xy(1.0, 1.0));
+        assert_vec_approx!(
+            fs.get_nodes()[0].position,
+            Vector2D::from_xy(1.0, 4.0),
+            1e-12
+        );
+    }
+
+    #[test]
+    pub fn test_set_velocity() {
+        let isolated = Node::new().position(Vector2D::from_xy(1.0, 2.0)).build();
+        let mut fs = ForceSimulation::new(vec![isolated], vec![], 0.5, 1.0, 1.0);
+        let v = Vector2D::from_xy(3.0, -4.0);
+        fs.set_velocity(0, v);
+        assert_eq!(fs.velocities[0], v);
+
+        // with no forces, a step moves the node by v * time_step
+        fs.step();
+        assert_eq!(fs.get_nodes()[0].position, Vector2D::from_xy(2.5, 0.0));
+        assert_eq!(fs.get_nodes()[0].velocity, v);
+
+        let (nodes, edges) = setup();
+        let mut fs = ForceSimulation::new(nodes, edges, 0.5, 1.0, 1.0);
+        fs.set_all_velocities(v);
+        assert!(fs.get_nodes().iter().all(|node| node.velocity == v));
+        assert!(fs.velocities.iter().all(|velocity| *velocity == v));
+    }
+
+    #[test]
+    pub fn test_shuffle_ids() {
+        // ids equal to indices, which hides any confusion between the two
+        let nodes: Vec<Node> = (0..6)
+            .map(|i| {
+                Node::new()
+                    .id(i)
+                    .position(Vector2D::from_xy(i as f64, 0.0))
+                    .build()
+            })
+            .collect();
+        let edges: Vec<Edge> = (1..6).map(|i| Edge::new(i - 1, i, 1.0)).collect();
+        let build = || ForceSimulation::new(nodes.clone(), edges.clone(), 1.0, 1.0, 1.0);
+        let mut fs = build();
+
+        fs.shuffle_ids(42);
+        let ids: Vec<usize> = fs.get_nodes().iter().map(|node| node.id).collect();
+        assert_ne!(ids, (0..6).collect::<Vec<usize>>());
+        let mut sorted = ids.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..6).collect::<Vec<usize>>());
+
+        // the same seed always gives the same permutation
+        let mut again = build();
+        again.shuffle_ids(42);
+        let again_ids: Vec<usize> = again.get_nodes().iter().map(|node| node.id).collect();
+        assert_eq!(again_ids, ids);
+
+        // id-based accessors find each node by its new id, and edges still use indices
+        for (idx, &id) in ids.iter().enumerate() {
+            let expected = Vector2D::from_xy(idx as f64, 0.0);
+            assert_eq!(fs.get_node_by_id(id).unwrap().position, expected);
+            assert_eq!(fs.position_of(id), Some(expected));
+        }
+        let exported = fs.export_positions();
+        fs.import_positions(&[(ids[3], -1.0, -1.0)]);
+        assert_eq!(fs.get_nodes()[3].position, Vector2D::from_xy(-1.0, -1.0));
+        fs.import_positions(&exported);
+        assert_eq!(fs.get_nodes()[3].position, Vector2D::from_xy(3.0, 0.0));
+        assert_eq!(fs.edges, edges);
+    }
+
+    #[test]
+    pub fn test_auto_time_step() {
+        // a tightly packed random graph, stepped with a time step far too large for it
+        let run = |auto_time_step: bool| {
+            let nodes: Vec<Node> = (0..20).map(|i| Node::new().id(i).build()).collect();
+            let edges: Vec<Edge> = (1..20).map(|i| Edge::new(i - 1, i, 1.0)).collect();
+            let params = SimParams {
+                time_step: 1.0,
+                auto_time_step,
+                ..SimParams::default()
+            };
+            let mut rng = StdRng::seed_from_u64(3);
+            let mut fs =
+                ForceSimulation::new_with_random_positions(nodes, edges, params, 0.5, &mut rng);
+            let suggested = fs.suggested_time_step();
+            let max_displacement = (0..50)
+                .map(|_| fs.step().max_displacement)
+                .fold(0.0, f64::max);
+            (suggested, max_displacement)
+        };
+
+        let (suggested, fixed) = run(false);
+        let (_, auto) = run(true);
+        assert!(suggested < 1.0);
+        assert!(fixed > 100.0, "fixed time step moved a node {}", fixed);
+        assert!(auto < 0.1, "automatic time step moved a node {}", auto);
+    }
+
+    #[test]
+    pub fn test_auto_style() {
+        // a star whose hub has the highest degree, with one edge heavier than the rest
+        let nodes: Vec<Node> = (0..4)
+            .map(|i| Node::at(i, Vector2D::from_xy(i as f64, 0.0)))
+            .collect();
+        let edges = vec![
+            Edge::new(0, 1, 0.5),
+            Edge::new(0, 2, -2.0),
+            Edge::new(0, 3, 1.0),
+            Edge::new(1, 2, 0.25),
+        ];
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, SimParams::default());
+        fs.auto_style(2.0..=8.0, 1.0..=4.0);
+
+        let radii: Vec<f64> = fs.get_nodes().iter().map(|node| node.radius).collect();
+        assert_eq!(radii, vec![8.0, 5.0, 5.0, 2.0]);
+        let widths: Vec<f64> = fs.get_edges().iter().map(|edge| edge.width).collect();
+        assert_eq!(widths[1], 4.0);
+        assert_eq!(widths[3], 1.0);
+        assert!(widths[0] < widths[2] && widths[2] < widths[1]);
+
+        // with nothing to tell the nodes apart, every node gets the middle of the range
+        let (nodes, edges) = setup();
+        let mut fs = ForceSimulation::new_with_params(nodes, edges, SimParams::default());
+        fs.auto_style(2.0..=8.0, 1.0..=4.0);
+        assert!(fs.get_nodes().iter().all(|node| node.radius == 5.0));
+    }
+
+    #[test]
+    pub fn test_edge_label_anchor() {
+        let nodes = vec![
+            Node::new().position(Vector2D::from_xy(0.0, 1.0)).build(),
+            Node::new().position(Vector2D::from_xy(4.0, 1.0)).build(),
+        ];
+        let edges = vec![Edge::new(0, 1, 1.0), Edge::new(1, 0, 1.0)];
+        let fs = ForceSimulation::new(nodes, edges, 1.0, 1.0, 1.0);
+
+        assert_eq!(fs.edge_midpoint(0), Some(Vector2D::from_xy(2.0, 1.0)));
+        assert_eq!(fs.edge_midpoint(2), None);
+
+        // a horizontal edge pointing right has its label above it, and pointing left below it
+        assert_vec_approx!(
+            fs.edge_label_anchor(0, 0.5).unwrap(),
+            Vector2D::from_xy(2.0, 1.5),
+            1e-12
+        );
+        assert_vec_approx!(
+            fs.edge_label_anchor(1, 0.5).unwrap(),
+            Vector2D::from_xy(2.0, 0.5),
+            1e-12
+        );
+        assert_eq!(fs.edge_label_anchor(2, 0.5), None);
+    }
+
+    /// Checks single steps against the closed-form kinematics `p0 + v0 * t + a * t^2 / 2` at full
+    /// precision, rather than comparing rounded values.
+    pub mod kinematics {
+        use super::*;
+
+        /// Two nodes on the x axis joined by an edge of weight 2, so both have mass 2. With an
+        /// attraction constant of 0.375 the net force between them is `0.375 * 2 * 2 - 2 * 2 /
+        /// 2^2 = 0.5`, pulling them together, and each accelerates at 0.25 toward the other.
+        fn isolated_pair(v0: Vector2D<f64>, time_step: f64) -> ForceSimulation {
+            let nodes = vec![
+                Node::new().position(Vector2D::from_xy(0.0, 0.0)).build(),
+                Node::new()
+                    .position(Vector2D::from_xy(2.0, 0.0))
+                    .velocity(v0)
+                    .build(),
+            ];
+            let params = SimParams {
+                time_step,
+                attraction_constant: 0.375,
+                ..SimParams::default()
+            };
+            ForceSimulation::new_with_params(nodes, vec![Edge::new(0, 1, 2.0)], params)
+        }
+
+        #[test]
+        pub fn test_chg_in_position_closed_form() {
+            let fs = isolated_pair(Vector2D::from_xy(0.0, 0.0), 0.1);
+            let nodes = fs.get_nodes();
+
+            // a * t^2 / 2 = -0.25 * 0.01 / 2 along x
+            let delta_p =
+                fs.chg_in_position_from_force_n1_exerts_on_n2(&nodes[0], &nodes[1], 2.0, 0.1);
+            assert_vec_approx!(delta_p, Vector2D::from_xy(-0.00125, 0.0), 1e-15);
+
+            // a * t = -0.25 * 0.1 along x
+            let delta_v =
+                fs.chg_in_velocity_from_force_n1_exerts_on_n2(&nodes[0], &nodes[1], 2.0, 0.1);
+            assert_vec_approx!(delta_v, Vector2D::from_xy(-0.025, 0.0), 1e-15);
+        }
+
+        #[test]
+        pub fn test_euler_step_closed_form() {
+            let v0 = Vector2D::from_xy(1.0, 0.5);
+            let mut fs = isolated_pair(v0, 0.1);
+            fs.step();
+            let nodes = fs.get_nodes();
+
+            // the moving node: p0 + v0 * t + a * t^2 / 2 and v0 + a * t
+            let a = Vector2D::from_xy(-0.25, 0.0);
+            let expected_p = Vector2D::from_xy(2.0, 0.0) + v0 * 0.1 + a * 0.01 / 2.0;
+            assert_vec_approx!(nodes[1].position, expected_p, 1e-15);
+            assert_vec_approx!(nodes[1].position, Vector2D::from_xy(2.09875, 0.05), 1e-15);
+            assert_vec_approx!(nodes[1].velocity, Vector2D::from_xy(0.975, 0.5), 1e-15);
+
+            // the node at rest: a * t^2 / 2 and a * t, in the opposite direction
+            assert_vec_approx!(nodes[0].position, Vector2D::from_xy(0.00125, 0.0), 1e-15);
+            assert_vec_approx!(nodes[0].velocity, Vector2D::from_xy(0.025, 0.0), 1e-15);
+        }
+
+        #[test]
+        pub fn test_momentum_conserved_over_a_step() {
+            // the pairwise forces are equal and opposite, so with equal masses the total
+            // velocity stays at its initial value
+            let v0 = Vector2D::from_xy(1.0, 0.5);
+            let mut fs = isolated_pair(v0, 0.1);
+            fs.step_n(10);
+            let total: Vector2D<f64> = fs
+                .get_nodes()
+                .iter()
+                .fold(Vector2D::from_xy(0.0, 0.0), |total, node| {
+                    total + node.velocity
+                });
+            assert_vec_approx!(total, v0, 1e-12);
+        }
     }
 }
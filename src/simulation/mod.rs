@@ -1 +1,4 @@
 pub mod force_simulation;
+#[cfg(feature = "image")]
+pub mod raster;
+pub mod sim_params;
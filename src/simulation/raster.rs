@@ -0,0 +1,217 @@
+use crate::error::{Error, Result};
+use crate::math::vector_2d::Vector2D;
+use crate::simulation::force_simulation::ForceSimulation;
+
+/// Color of the background, and of anything whose color is not a `#rrggbb` hex string.
+const BACKGROUND: [u8; 3] = [255, 255, 255];
+const DEFAULT_EDGE_COLOR: [u8; 3] = [0, 0, 0];
+const DEFAULT_NODE_COLOR: [u8; 3] = [68, 119, 170];
+
+/// Fraction of each side of the image left blank around the layout.
+const MARGIN: f64 = 0.1;
+
+/// Largest number of pixels an image may have, e.g. 16384 x 16384, which takes about 800 MB as
+/// RGB. Larger images are refused rather than exhausting memory.
+const MAX_PIXELS: usize = 1 << 28;
+
+/// An RGB bitmap that lines and filled circles can be drawn on.
+struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    /// Creates a blank canvas, or returns [Error::ImageTooLarge] if it would have more than
+    /// [MAX_PIXELS] pixels.
+    fn new(width: u32, height: u32) -> Result<Self> {
+        let n_pixels = (width as usize)
+            .checked_mul(height as usize)
+            .filter(|&n_pixels| n_pixels <= MAX_PIXELS)
+            .ok_or(Error::ImageTooLarge(width, height))?;
+        Ok(Canvas {
+            width,
+            height,
+            pixels: BACKGROUND.repeat(n_pixels),
+        })
+    }
+
+    /// Colors the pixel at `(x, y)`, ignoring pixels outside the image.
+    fn put(&mut self, x: i64, y: i64, color: [u8; 3]) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let offset = 3 * (y as usize * self.width as usize + x as usize);
+        self.pixels[offset..offset + 3].copy_from_slice(&color);
+    }
+
+    /// Draws a one-pixel line from `start` to `end` with Bresenham's algorithm.
+    fn line(&mut self, start: Vector2D<f64>, end: Vector2D<f64>, color: [u8; 3]) {
+        let (mut x, mut y) = (start.x.round() as i64, start.y.round() as i64);
+        let (x1, y1) = (end.x.round() as i64, end.y.round() as i64);
+        let (dx, dy) = ((x1 - x).abs(), -(y1 - y).abs());
+        let (sx, sy) = ((x1 - x).signum(), (y1 - y).signum());
+        let mut error = dx + dy;
+        loop {
+            self.put(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            if 2 * error >= dy {
+                error += dy;
+                x += sx;
+            }
+            if 2 * error <= dx {
+                error += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Fills the circle of `radius` pixels around `center`.
+    fn disc(&mut self, center: Vector2D<f64>, radius: f64, color: [u8; 3]) {
+        let (min_x, max_x) = ((center.x - radius).floor(), (center.x + radius).ceil());
+        let (min_y, max_y) = ((center.y - radius).floor(), (center.y + radius).ceil());
+        for y in min_y as i64..=max_y as i64 {
+            for x in min_x as i64..=max_x as i64 {
+                if Vector2D::from_xy(x as f64, y as f64).distance(&center) <= radius {
+                    self.put(x, y, color);
+                }
+            }
+        }
+    }
+
+    fn to_png(&self) -> std::result::Result<Vec<u8>, png::EncodingError> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+        Ok(bytes)
+    }
+}
+
+/// Parses a `#rrggbb` hex color, the only color format the rasterizer understands.
+fn parse_hex_color(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+impl ForceSimulation {
+    /// Rasterizes the visible nodes, as filled circles, and the edges between them, as lines,
+    /// into a `width` x `height` PNG, for a thumbnail without a browser. The nodes' bounding box
+    /// is scaled uniformly to fit the image, leaving a margin of 10% on each side, with y
+    /// pointing down as in SVG. Colors given as `#rrggbb` are used; any other color, such as a
+    /// named one, is drawn in a default color. A side of 0 pixels is treated as 1. Returns
+    /// [Error::ImageTooLarge] for an image of more than 2^28 pixels.
+    pub fn to_png(&self, width: u32, height: u32) -> Result<Vec<u8>> {
+        let (width, height) = (width.max(1), height.max(1));
+        let mut canvas = Canvas::new(width, height)?;
+
+        let (min, max) = self
+            .bounding_box()
+            .unwrap_or((Vector2D::from_xy(0.0, 0.0), Vector2D::from_xy(0.0, 0.0)));
+        let (layout_width, layout_height) = (max.x - min.x, max.y - min.y);
+        let fit = |pixels: u32, extent: f64| {
+            if extent > 0.0 {
+                pixels as f64 * (1.0 - 2.0 * MARGIN) / extent
+            } else {
+                f64::INFINITY
+            }
+        };
+        let scale = fit(width, layout_width).min(fit(height, layout_height));
+        let scale = if scale.is_finite() { scale } else { 1.0 };
+
+        // Center the layout in the image
+        let layout_center = Vector2D::from_xy((min.x + max.x) / 2.0, (min.y + max.y) / 2.0);
+        let image_center = Vector2D::from_xy(width as f64 / 2.0, height as f64 / 2.0);
+        let to_pixels = |position: Vector2D<f64>| (position - layout_center) * scale + image_center;
+
+        let nodes = self.get_nodes();
+        for edge in self.get_edges() {
+            let (n1, n2) = (&nodes[edge.node1_idx], &nodes[edge.node2_idx]);
+            if n1.visible && n2.visible {
+                let color = parse_hex_color(&edge.color).unwrap_or(DEFAULT_EDGE_COLOR);
+                canvas.line(to_pixels(n1.position), to_pixels(n2.position), color);
+            }
+        }
+        for node in self.visible_nodes() {
+            let color = parse_hex_color(&node.fill).unwrap_or(DEFAULT_NODE_COLOR);
+            canvas.disc(
+                to_pixels(node.position),
+                (node.radius * scale).max(1.0),
+                color,
+            );
+        }
+
+        Ok(canvas.to_png()?)
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use crate::graph::{edge::Edge, node::Node};
+
+    /// Decodes a PNG, returning its dimensions and RGB pixels.
+    fn decode(bytes: &[u8]) -> (u32, u32, Vec<u8>) {
+        let mut reader = png::Decoder::new(bytes).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        pixels.truncate(info.buffer_size());
+        (info.width, info.height, pixels)
+    }
+
+    #[test]
+    pub fn test_to_png() {
+        let nodes = vec![
+            Node::at(0, Vector2D::from_xy(0.0, 0.0)).with_fill("#ff0000"),
+            Node::at(1, Vector2D::from_xy(10.0, 0.0)),
+            Node::at(2, Vector2D::from_xy(5.0, 5.0)),
+        ];
+        let edges = vec![Edge::between(0, 1, 1.0), Edge::between(1, 2, 1.0)];
+        let fs = ForceSimulation::new(nodes, edges, 0.01, 1.0, 1.0);
+
+        let bytes = fs.to_png(64, 48).unwrap();
+        assert_eq!(&bytes[..8], b"\x89PNG\r\n\x1a\n");
+        let (width, height, pixels) = decode(&bytes);
+        assert_eq!((width, height), (64, 48));
+
+        // the 10-unit-wide layout is scaled by 5.12 to fill 80% of the width, which puts node 0
+        // at (6.4, 11.2) and node 1 at (57.6, 11.2), with a radius of 5.12 pixels
+        let pixel = |x: usize, y: usize| &pixels[3 * (y * 64 + x)..3 * (y * 64 + x) + 3];
+        assert_eq!(pixel(6, 14), &[255, 0, 0]);
+        assert_eq!(pixel(0, 0), &BACKGROUND);
+        assert_eq!(pixel(58, 14), &DEFAULT_NODE_COLOR);
+    }
+
+    #[test]
+    pub fn test_to_png_degenerate() {
+        let empty = ForceSimulation::new(vec![], vec![], 0.01, 1.0, 1.0);
+        let (width, height, pixels) = decode(&empty.to_png(0, 5).unwrap());
+        assert_eq!((width, height), (1, 5));
+        assert!(pixels.chunks(3).all(|pixel| pixel == BACKGROUND));
+
+        assert_eq!(parse_hex_color("#336699"), Some([0x33, 0x66, 0x99]));
+        assert_eq!(parse_hex_color("transparent"), None);
+        assert_eq!(parse_hex_color("#3366"), None);
+    }
+
+    #[test]
+    pub fn test_to_png_too_large() {
+        let fs = ForceSimulation::new(vec![], vec![], 0.01, 1.0, 1.0);
+        assert!(matches!(
+            fs.to_png(70000, 70000),
+            Err(Error::ImageTooLarge(70000, 70000))
+        ));
+        assert!(matches!(
+            fs.to_png(u32::MAX, u32::MAX),
+            Err(Error::ImageTooLarge(_, _))
+        ));
+        assert!(fs.to_png(1, 1 << 20).is_ok());
+    }
+}